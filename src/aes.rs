@@ -0,0 +1,324 @@
+// As with base64, a library could do this for us, but the later challenges are much more
+// instructive with every step of the cipher in plain view. Follows FIPS-197 throughout.
+
+use std::convert::TryFrom;
+use block_cipher::BlockCipher;
+use error::CryptoError;
+
+pub const AES_BLOCK_SIZE: usize = 16;
+
+// Multiplication by x (i.e. 0x02) in GF(2^8) modulo the AES polynomial x^8 + x^4 + x^3 + x + 1
+const fn xtime(a: u8) -> u8 {
+    (a << 1) ^ (if a & 0x80 != 0 { 0x1b } else { 0x00 })
+}
+
+const fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    product
+}
+
+// Multiplicative inverse in GF(2^8) as a^254, with 0 mapping to 0
+const fn gf_inverse(a: u8) -> u8 {
+    let mut result = 1;
+    let mut i = 0;
+    while i < 254 {
+        result = gf_mul(result, a);
+        i += 1;
+    }
+    result
+}
+
+const fn generate_sbox() -> [u8; 256] {
+    let mut sbox = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        let b = gf_inverse(i as u8);
+        sbox[i] = b ^ b.rotate_left(1) ^ b.rotate_left(2) ^ b.rotate_left(3) ^ b.rotate_left(4) ^
+                  0x63;
+        i += 1;
+    }
+    sbox
+}
+
+const fn invert_sbox(sbox: &[u8; 256]) -> [u8; 256] {
+    let mut inverse = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        inverse[sbox[i] as usize] = i as u8;
+        i += 1;
+    }
+    inverse
+}
+
+static SBOX: [u8; 256] = generate_sbox();
+static INVERSE_SBOX: [u8; 256] = invert_sbox(&SBOX);
+
+// An expanded AES key, able to encrypt and decrypt single blocks. The state is held as in the
+// standard, column by column, so input byte i is row i % 4 of column i / 4.
+#[derive(Clone)]
+pub struct Aes {
+    round_keys: Vec<[u8; AES_BLOCK_SIZE]>,
+}
+
+impl Aes {
+    // Expands a 16, 24 or 32 byte key for AES-128, AES-192 or AES-256 respectively.
+    pub fn new(key: &[u8]) -> Result<Aes, CryptoError> {
+        let key_words = match key.len() {
+            16 | 24 | 32 => key.len() / 4,
+            _ => return Err(CryptoError::InvalidKeyLength(key.len())),
+        };
+        let rounds = key_words + 6;
+        let total_words = 4 * (rounds + 1);
+
+        let mut words: Vec<[u8; 4]> = key.chunks(4).map(|c| [c[0], c[1], c[2], c[3]]).collect();
+        let mut round_constant = 0x01;
+        for i in key_words..total_words {
+            let mut temp = words[i - 1];
+            if i % key_words == 0 {
+                temp = [SBOX[temp[1] as usize] ^ round_constant,
+                        SBOX[temp[2] as usize],
+                        SBOX[temp[3] as usize],
+                        SBOX[temp[0] as usize]];
+                round_constant = xtime(round_constant);
+            } else if key_words > 6 && i % key_words == 4 {
+                temp = [SBOX[temp[0] as usize],
+                        SBOX[temp[1] as usize],
+                        SBOX[temp[2] as usize],
+                        SBOX[temp[3] as usize]];
+            }
+            let previous = words[i - key_words];
+            words.push([previous[0] ^ temp[0],
+                        previous[1] ^ temp[1],
+                        previous[2] ^ temp[2],
+                        previous[3] ^ temp[3]]);
+        }
+
+        let round_keys = words.chunks(4)
+            .map(|round_words| {
+                let mut round_key = [0u8; AES_BLOCK_SIZE];
+                for (i, word) in round_words.iter().enumerate() {
+                    round_key[4 * i..4 * i + 4].copy_from_slice(word);
+                }
+                round_key
+            })
+            .collect();
+        Ok(Aes { round_keys })
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.round_keys.len() - 1
+    }
+
+    pub fn key_size(&self) -> usize {
+        (self.rounds() - 6) * 4
+    }
+}
+
+fn as_state(block: &mut [u8]) -> Result<&mut [u8; AES_BLOCK_SIZE], CryptoError> {
+    let actual = block.len();
+    <&mut [u8; AES_BLOCK_SIZE]>::try_from(block)
+        .map_err(|_| CryptoError::InvalidBlockLength { expected: AES_BLOCK_SIZE, actual })
+}
+
+impl BlockCipher for Aes {
+    fn block_size(&self) -> usize {
+        AES_BLOCK_SIZE
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) -> Result<(), CryptoError> {
+        let state = as_state(block)?;
+        add_round_key(state, &self.round_keys[0]);
+        for round in 1..self.rounds() {
+            sub_bytes(state, &SBOX);
+            shift_rows(state);
+            mix_columns(state);
+            add_round_key(state, &self.round_keys[round]);
+        }
+        sub_bytes(state, &SBOX);
+        shift_rows(state);
+        add_round_key(state, &self.round_keys[self.rounds()]);
+        Ok(())
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) -> Result<(), CryptoError> {
+        let state = as_state(block)?;
+        add_round_key(state, &self.round_keys[self.rounds()]);
+        for round in (1..self.rounds()).rev() {
+            inverse_shift_rows(state);
+            sub_bytes(state, &INVERSE_SBOX);
+            add_round_key(state, &self.round_keys[round]);
+            inverse_mix_columns(state);
+        }
+        inverse_shift_rows(state);
+        sub_bytes(state, &INVERSE_SBOX);
+        add_round_key(state, &self.round_keys[0]);
+        Ok(())
+    }
+}
+
+fn add_round_key(state: &mut [u8; AES_BLOCK_SIZE], round_key: &[u8; AES_BLOCK_SIZE]) {
+    for (s, k) in state.iter_mut().zip(round_key.iter()) {
+        *s ^= k;
+    }
+}
+
+fn sub_bytes(state: &mut [u8; AES_BLOCK_SIZE], sbox: &[u8; 256]) {
+    for s in state.iter_mut() {
+        *s = sbox[*s as usize];
+    }
+}
+
+// Row r is rotated left by r columns
+fn shift_rows(state: &mut [u8; AES_BLOCK_SIZE]) {
+    let original = *state;
+    for column in 0..4 {
+        for row in 1..4 {
+            state[row + 4 * column] = original[row + 4 * ((column + row) % 4)];
+        }
+    }
+}
+
+fn inverse_shift_rows(state: &mut [u8; AES_BLOCK_SIZE]) {
+    let original = *state;
+    for column in 0..4 {
+        for row in 1..4 {
+            state[row + 4 * ((column + row) % 4)] = original[row + 4 * column];
+        }
+    }
+}
+
+fn mix_columns(state: &mut [u8; AES_BLOCK_SIZE]) {
+    for column in state.chunks_mut(4) {
+        let c = [column[0], column[1], column[2], column[3]];
+        column[0] = gf_mul(c[0], 2) ^ gf_mul(c[1], 3) ^ c[2] ^ c[3];
+        column[1] = c[0] ^ gf_mul(c[1], 2) ^ gf_mul(c[2], 3) ^ c[3];
+        column[2] = c[0] ^ c[1] ^ gf_mul(c[2], 2) ^ gf_mul(c[3], 3);
+        column[3] = gf_mul(c[0], 3) ^ c[1] ^ c[2] ^ gf_mul(c[3], 2);
+    }
+}
+
+fn inverse_mix_columns(state: &mut [u8; AES_BLOCK_SIZE]) {
+    for column in state.chunks_mut(4) {
+        let c = [column[0], column[1], column[2], column[3]];
+        column[0] = gf_mul(c[0], 14) ^ gf_mul(c[1], 11) ^ gf_mul(c[2], 13) ^ gf_mul(c[3], 9);
+        column[1] = gf_mul(c[0], 9) ^ gf_mul(c[1], 14) ^ gf_mul(c[2], 11) ^ gf_mul(c[3], 13);
+        column[2] = gf_mul(c[0], 13) ^ gf_mul(c[1], 9) ^ gf_mul(c[2], 14) ^ gf_mul(c[3], 11);
+        column[3] = gf_mul(c[0], 11) ^ gf_mul(c[1], 13) ^ gf_mul(c[2], 9) ^ gf_mul(c[3], 14);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aes::{Aes, AES_BLOCK_SIZE, SBOX, INVERSE_SBOX};
+    use block_cipher::BlockCipher;
+    use error::CryptoError;
+    use rustc_serialize::hex::FromHex;
+
+    fn block(hex: &str) -> [u8; AES_BLOCK_SIZE] {
+        let bytes = hex.from_hex().unwrap();
+        let mut block = [0u8; AES_BLOCK_SIZE];
+        block.copy_from_slice(&bytes);
+        block
+    }
+
+    // Checks encryption of plaintext gives ciphertext, and decryption gives it back
+    fn check_vector(key: &str, plaintext: &str, ciphertext: &str) {
+        let aes = Aes::new(&key.from_hex().unwrap()).unwrap();
+        let mut state = block(plaintext);
+        aes.encrypt_block(&mut state).unwrap();
+        assert_eq!(state, block(ciphertext));
+        aes.decrypt_block(&mut state).unwrap();
+        assert_eq!(state, block(plaintext));
+    }
+
+    #[test]
+    fn sbox() {
+        assert_eq!(SBOX[0x00], 0x63);
+        assert_eq!(SBOX[0x53], 0xed);
+        assert_eq!(SBOX[0xff], 0x16);
+        assert_eq!(INVERSE_SBOX[0x63], 0x00);
+        assert_eq!(INVERSE_SBOX[0xed], 0x53);
+    }
+
+    #[test]
+    fn fips_197_key_expansion() {
+        // Appendix A.1
+        let aes = Aes::new(&"2b7e151628aed2a6abf7158809cf4f3c".from_hex().unwrap()).unwrap();
+        assert_eq!(aes.rounds(), 10);
+        assert_eq!(aes.key_size(), 16);
+        assert_eq!(aes.round_keys[1], block("a0fafe1788542cb123a339392a6c7605"));
+        assert_eq!(aes.round_keys[10], block("d014f9a8c9ee2589e13f0cc8b6630ca6"));
+
+        // Appendix A.2 and A.3, final words
+        let aes = Aes::new(&"8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b"
+            .from_hex().unwrap()).unwrap();
+        assert_eq!(aes.rounds(), 12);
+        assert_eq!(aes.round_keys[12][12..], [0x01, 0x00, 0x22, 0x02]);
+        let aes = Aes::new(&"603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4"
+            .from_hex().unwrap()).unwrap();
+        assert_eq!(aes.rounds(), 14);
+        assert_eq!(aes.round_keys[14][12..], [0x70, 0x6c, 0x63, 0x1e]);
+    }
+
+    #[test]
+    fn fips_197_vectors() {
+        // Appendix B
+        check_vector("2b7e151628aed2a6abf7158809cf4f3c",
+                     "3243f6a8885a308d313198a2e0370734",
+                     "3925841d02dc09fbdc118597196a0b32");
+        // Appendix C.1 to C.3
+        check_vector("000102030405060708090a0b0c0d0e0f",
+                     "00112233445566778899aabbccddeeff",
+                     "69c4e0d86a7b0430d8cdb78070b4c55a");
+        check_vector("000102030405060708090a0b0c0d0e0f1011121314151617",
+                     "00112233445566778899aabbccddeeff",
+                     "dda97ca4864cdfe06eaf70a0ec0d7191");
+        check_vector("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                     "00112233445566778899aabbccddeeff",
+                     "8ea2b7ca516745bfeafc49904b496089");
+    }
+
+    #[test]
+    fn nist_known_answer_tests() {
+        // AESAVS GFSbox
+        check_vector("00000000000000000000000000000000",
+                     "f34481ec3cc627bacd5dc3fb08f273e6",
+                     "0336763e966d92595a567cc9ce537f5e");
+        check_vector("000000000000000000000000000000000000000000000000",
+                     "1b077a6af4b7f98229de786d7516b639",
+                     "275cfc0413d8ccb70513c3859b1d0f72");
+        check_vector("0000000000000000000000000000000000000000000000000000000000000000",
+                     "014730f80ac625fe84f026c60bfd547d",
+                     "5c9d844ed46f9885085e5d6a4f94c7d7");
+        // AESAVS KeySbox and VarTxt
+        check_vector("10a58869d74be5a374cf867cfb473859",
+                     "00000000000000000000000000000000",
+                     "6d251e6944b051e04eaa6fb4dbf78465");
+        check_vector("00000000000000000000000000000000",
+                     "80000000000000000000000000000000",
+                     "3ad78e726c1ec02b7ebfe92b23d9ec34");
+    }
+
+    #[test]
+    fn invalid_key_sizes() {
+        assert_eq!(Aes::new(&[0u8; 15]).err(), Some(CryptoError::InvalidKeyLength(15)));
+        assert!(Aes::new(&[0u8; 20]).is_err());
+        assert!(Aes::new(&[]).is_err());
+    }
+
+    #[test]
+    fn wrong_block_length() {
+        let aes = Aes::new(&[0u8; 16]).unwrap();
+        assert_eq!(aes.block_size(), AES_BLOCK_SIZE);
+        assert_eq!(aes.encrypt_block(&mut [0u8; 15]),
+                   Err(CryptoError::InvalidBlockLength { expected: 16, actual: 15 }));
+        assert!(aes.decrypt_block(&mut [0u8; 17]).is_err());
+    }
+}
@@ -14,11 +14,12 @@ static BASE64_ENCODE_TABLE : [char; 64] = ['A', 'B', 'C', 'D', 'E', 'F',
 									'2', '3', '4', '5', '6', '7',
 									'8', '9', '+', '/'];
 
-trait Base64Encodable {
+pub trait Base64Encodable {
 	fn to_base64_char_vec(&self) -> Vec<char>;
 }
 
-trait Base64Decodable {
+#[allow(clippy::wrong_self_convention)]
+pub trait Base64Decodable {
 	fn from_base64_to_u8_vec(&self) -> Result<Vec<u8>, String>;
 }
 
@@ -29,7 +30,7 @@ impl Base64Encodable for Vec<u8> {
 		let mut i : usize = 0;
 
 		// Handle all but the last bytes of the array
-		while i < num_bytes_to_encode - 3 {
+		while i + 3 < num_bytes_to_encode {
 			let first_char_index : usize = ((self[i] & 0xfc) >> 2) as usize;
 			let second_char_index : usize = (((self[i] & 0x03) << 4) | ((self[i + 1] & 0xf0) >> 4)) as usize;
 			let third_char_index : usize = (((self[i + 1] & 0x0f) << 2) | (self[i + 2] >> 6)) as usize;
@@ -91,6 +92,10 @@ impl Base64Decodable for Vec<char> {
 
 		let mut decoded_bytes : Vec<u8> = vec!();
 
+		if num_bytes_to_decode == 0 {
+			return Ok(decoded_bytes);
+		}
+
 		let mut base64_decode_map : HashMap<char, u8> = HashMap::with_capacity(BASE64_ENCODE_TABLE.len()); 
 
 		for (i, &c) in BASE64_ENCODE_TABLE.iter().enumerate() {
@@ -98,7 +103,7 @@ impl Base64Decodable for Vec<char> {
 		}
 
 		let mut i : usize = 0;
-		while i < num_bytes_to_decode - 4 {
+		while i + 4 < num_bytes_to_decode {
 			let first_value = base64_decode_map.get(&self[i]);
 			let second_value = base64_decode_map.get(&self[i + 1]);
 			let third_value = base64_decode_map.get(&self[i + 2]);
@@ -262,4 +267,17 @@ mod tests {
 			}
 		}
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn test_short_inputs() {
+		let inputs : Vec<Vec<u8>> = vec![vec![], vec![0x4d], vec![0x4d, 0x61], vec![0x4d, 0x61, 0x6e]];
+		let expected_outputs = ["", "TQ==", "TWE=", "TWFu"];
+
+		for (input, expected_output) in inputs.iter().zip(expected_outputs.iter()) {
+			let encoded_string : String = input.to_base64_char_vec().into_iter().collect();
+			assert_eq!(&encoded_string, expected_output);
+			let encoded_chars : Vec<char> = expected_output.chars().collect();
+			assert_eq!(&encoded_chars.from_base64_to_u8_vec().unwrap(), input);
+		}
+	}
+}
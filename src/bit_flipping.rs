@@ -0,0 +1,279 @@
+use rand::Rng;
+use aes::{Aes, AES_BLOCK_SIZE};
+use cbc;
+use ctr::{Ctr, CounterLayout};
+use fixed_xor::{FixedXor, fixed_xor_in_place};
+use oracle::{self, Oracle, BlockMode};
+use oracle_profile;
+use stream_cipher::StreamCipher;
+
+// Bit flipping: in CBC each plaintext block is XORed with the previous ciphertext block after
+// decryption, so XORing a delta into ciphertext block i XORs it into plaintext block i + 1.
+// Block i itself decrypts to garbage, so attacks sacrifice a block of their own input to it.
+// In CTR the plaintext is XORed with the keystream alone, so a delta XORed into a ciphertext
+// byte XORs into the same plaintext byte and nothing else changes.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitFlip {
+    pub ciphertext: Vec<u8>,
+    // Plaintext block that decrypts to garbage once the flip is applied
+    pub scrambled_block: usize,
+}
+
+// Edits a CBC ciphertext so the known plaintext at position decrypts as desired instead. The
+// edited bytes must lie in one block, and not the first, which could only be changed through
+// the IV.
+pub fn cbc_bit_flip(ciphertext: &[u8],
+                    block_size: usize,
+                    position: usize,
+                    known: &[u8],
+                    desired: &[u8])
+                    -> Result<BitFlip, String> {
+    if block_size == 0 {
+        return Err("Block size must be positive".to_owned());
+    }
+    let delta = known.fixed_xor(desired)?;
+    let target_block = position / block_size;
+    if delta.is_empty() || (position + delta.len() - 1) / block_size != target_block {
+        return Err("Flipped bytes must lie within a single block".to_owned());
+    }
+    if target_block == 0 {
+        return Err("The first block can only be flipped through the IV".to_owned());
+    }
+    if position + delta.len() > ciphertext.len() {
+        return Err("Flipped bytes lie past the end of the ciphertext".to_owned());
+    }
+
+    let mut flipped = ciphertext.to_vec();
+    let start = position - block_size;
+    fixed_xor_in_place(&mut flipped[start..start + delta.len()], &delta)?;
+    Ok(BitFlip { ciphertext: flipped, scrambled_block: target_block - 1 })
+}
+
+pub const COMMENT_PREFIX: &[u8] = b"comment1=cooking%20MCs;userdata=";
+pub const COMMENT_SUFFIX: &[u8] = b";comment2=%20like%20a%20pound%20of%20bacon";
+
+fn has_admin_field(comment: &[u8]) -> bool {
+    comment.split(|&b| b == b';').any(|field| field == b"admin=true")
+}
+
+// Challenge 16: AES-CBC under a fixed random key and IV of user data wrapped in comments,
+// with ';' and '=' in the user data escaped.
+pub struct CommentOracle {
+    aes: Aes,
+    iv: Vec<u8>,
+}
+
+impl CommentOracle {
+    pub fn new<R: Rng>(rng: &mut R) -> CommentOracle {
+        let key = oracle::random_bytes(rng, AES_BLOCK_SIZE);
+        CommentOracle {
+            aes: Aes::new(&key).expect("AES accepts a 16 byte key"),
+            iv: oracle::random_bytes(rng, AES_BLOCK_SIZE),
+        }
+    }
+
+    pub fn comment_for(user_data: &[u8]) -> Vec<u8> {
+        let mut comment = COMMENT_PREFIX.to_vec();
+        for &byte in user_data {
+            match byte {
+                b';' => comment.extend_from_slice(b"%3B"),
+                b'=' => comment.extend_from_slice(b"%3D"),
+                _ => comment.push(byte),
+            }
+        }
+        comment.extend_from_slice(COMMENT_SUFFIX);
+        comment
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(cbc::cbc_decrypt(&self.aes, &self.iv, ciphertext)?)
+    }
+
+    // Whether the decrypted comment has an admin=true field, ignoring any garbage elsewhere
+    pub fn is_admin(&self, ciphertext: &[u8]) -> bool {
+        self.decrypt(ciphertext).map(|plaintext| has_admin_field(&plaintext)).unwrap_or(false)
+    }
+}
+
+impl Oracle for CommentOracle {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        cbc::cbc_encrypt(&self.aes, &self.iv, &CommentOracle::comment_for(input))
+            .expect("padded input is whole blocks")
+    }
+}
+
+// Gets a payload the oracle would escape into its plaintext. Our input starts with a block of
+// filler, aligned to a block boundary, to be scrambled, followed by filler the length of the
+// payload, which is flipped into the payload.
+pub fn cbc_bit_flip_attack<O: Oracle>(oracle: &mut O, payload: &[u8]) -> Result<BitFlip, String> {
+    let profile = oracle_profile::profile_oracle(oracle)?;
+    if profile.mode != BlockMode::Cbc {
+        return Err("Bit flipping needs a CBC oracle".to_owned());
+    }
+    let block_size = profile.block_size;
+    if payload.len() > block_size {
+        return Err(format!("Payload must fit in a {} byte block", block_size));
+    }
+
+    let alignment = (block_size - profile.prefix_length % block_size) % block_size;
+    let filler = vec![b'A'; alignment + block_size + payload.len()];
+    let ciphertext = oracle.query(&filler);
+    let position = profile.prefix_length + alignment + block_size;
+    cbc_bit_flip(&ciphertext, block_size, position, &filler[..payload.len()], payload)
+}
+
+// Challenge 26: the comment oracle of challenge 16, but under AES-CTR with a fixed random key
+// and nonce.
+pub struct CtrCommentOracle {
+    ctr: Ctr<Aes>,
+}
+
+impl CtrCommentOracle {
+    pub fn new<R: Rng>(rng: &mut R) -> CtrCommentOracle {
+        let key = oracle::random_bytes(rng, AES_BLOCK_SIZE);
+        let nonce = oracle::random_bytes(rng, 8);
+        let aes = Aes::new(&key).expect("AES accepts a 16 byte key");
+        CtrCommentOracle {
+            ctr: Ctr::new(aes, CounterLayout::cryptopals(), &nonce, 0)
+                .expect("Cryptopals layout fills an AES block"),
+        }
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(self.ctr.process(ciphertext)?)
+    }
+
+    pub fn is_admin(&self, ciphertext: &[u8]) -> bool {
+        self.decrypt(ciphertext).map(|plaintext| has_admin_field(&plaintext)).unwrap_or(false)
+    }
+}
+
+impl Oracle for CtrCommentOracle {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        self.ctr.process(&CommentOracle::comment_for(input)).expect("CTR accepts any length")
+    }
+}
+
+// Edits a stream cipher ciphertext so the known plaintext at position decrypts as desired
+// instead. Only the edited bytes decrypt differently, since each depends on its keystream byte
+// alone.
+pub fn ctr_bit_flip(ciphertext: &[u8], position: usize, known: &[u8], desired: &[u8])
+                    -> Result<Vec<u8>, String> {
+    let delta = known.fixed_xor(desired)?;
+    let end = match position.checked_add(delta.len()) {
+        Some(end) if end <= ciphertext.len() => end,
+        _ => return Err("Flipped bytes lie past the end of the ciphertext".to_owned()),
+    };
+
+    let mut flipped = ciphertext.to_vec();
+    fixed_xor_in_place(&mut flipped[position..end], &delta)?;
+    Ok(flipped)
+}
+
+// Finds how many bytes a stream cipher oracle puts before our input: the first ciphertext byte
+// that changes with the input.
+fn find_stream_prefix_length<O: Oracle>(oracle: &mut O) -> Result<usize, String> {
+    let a = oracle.query(&[0]);
+    let b = oracle.query(&[1]);
+    a.iter().zip(&b).position(|(x, y)| x != y)
+        .ok_or_else(|| "Oracle ciphertext does not depend on its input".to_owned())
+}
+
+// Gets a payload the oracle would escape into its plaintext, by sending filler of the same
+// length and flipping it into the payload.
+pub fn ctr_bit_flip_attack<O: Oracle>(oracle: &mut O, payload: &[u8]) -> Result<Vec<u8>, String> {
+    let prefix_length = find_stream_prefix_length(oracle)?;
+    let filler = vec![b'A'; payload.len()];
+    let ciphertext = oracle.query(&filler);
+    ctr_bit_flip(&ciphertext, prefix_length, &filler, payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use aes::Aes;
+    use bit_flipping::{cbc_bit_flip, cbc_bit_flip_attack, ctr_bit_flip, ctr_bit_flip_attack,
+                       CommentOracle, CtrCommentOracle, COMMENT_PREFIX, COMMENT_SUFFIX};
+    use cbc;
+    use oracle::Oracle;
+
+    #[test]
+    fn flip_known_plaintext() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let iv = [7u8; 16];
+        let plaintext = b"0123456789abcdef:admin<true:xxxxlast block here";
+        let ciphertext = cbc::cbc_encrypt(&aes, &iv, plaintext).unwrap();
+
+        let flip = cbc_bit_flip(&ciphertext, 16, 16, b":admin<true:", b";admin=true;").unwrap();
+        assert_eq!(flip.scrambled_block, 0);
+        let decrypted = cbc::cbc_decrypt(&aes, &iv, &flip.ciphertext).unwrap();
+        assert!(decrypted[0..16] != plaintext[0..16]);
+        assert_eq!(&decrypted[16..], &b";admin=true;xxxxlast block here"[..]);
+    }
+
+    #[test]
+    fn flip_errors() {
+        let ciphertext = [0u8; 48];
+        assert!(cbc_bit_flip(&ciphertext, 16, 4, b"a", b"b").is_err());
+        assert!(cbc_bit_flip(&ciphertext, 16, 30, b"abc", b"def").is_err());
+        assert!(cbc_bit_flip(&ciphertext, 16, 47, b"ab", b"cd").is_err());
+        assert!(cbc_bit_flip(&ciphertext, 16, 20, b"ab", b"c").is_err());
+        assert!(cbc_bit_flip(&ciphertext, 16, 20, b"", b"").is_err());
+        assert!(cbc_bit_flip(&ciphertext, 0, 20, b"a", b"b").is_err());
+    }
+
+    #[test]
+    fn oracle_escapes() {
+        let mut rng = StdRng::seed_from_u64(40);
+        let mut oracle = CommentOracle::new(&mut rng);
+        let ciphertext = oracle.query(b";admin=true");
+        assert!(!oracle.is_admin(&ciphertext));
+        let plaintext = oracle.decrypt(&ciphertext).unwrap();
+        assert!(plaintext.starts_with(COMMENT_PREFIX));
+        assert_eq!(&plaintext[COMMENT_PREFIX.len()..COMMENT_PREFIX.len() + 15], b"%3Badmin%3Dtrue");
+    }
+
+    #[test]
+    fn challenge_16() {
+        let mut rng = StdRng::seed_from_u64(40);
+        let mut oracle = CommentOracle::new(&mut rng);
+        let flip = cbc_bit_flip_attack(&mut oracle, b";admin=true").unwrap();
+        assert!(oracle.is_admin(&flip.ciphertext));
+        // The prefix fills two blocks, so our sacrificial block is the third
+        assert_eq!(flip.scrambled_block, 2);
+
+        let plaintext = oracle.decrypt(&flip.ciphertext).unwrap();
+        assert_eq!(&plaintext[..32], COMMENT_PREFIX);
+        assert_eq!(&plaintext[48..59], b";admin=true");
+    }
+
+    #[test]
+    fn ctr_flip_known_plaintext() {
+        let ciphertext = b"\x00\x01\x02\x03\x04\x05";
+        let flipped = ctr_bit_flip(ciphertext, 1, b"abcd", b"abXY").unwrap();
+        assert_eq!(flipped, [0x00, 0x01, 0x02, 0x03 ^ b'c' ^ b'X', 0x04 ^ b'd' ^ b'Y', 0x05]);
+        assert!(ctr_bit_flip(ciphertext, 4, b"abc", b"xyz").is_err());
+        assert!(ctr_bit_flip(ciphertext, 0, b"abc", b"xy").is_err());
+        assert!(ctr_bit_flip(ciphertext, usize::MAX, b"abc", b"xyz").is_err());
+    }
+
+    #[test]
+    fn challenge_26() {
+        let mut rng = StdRng::seed_from_u64(44);
+        let mut oracle = CtrCommentOracle::new(&mut rng);
+        let escaped = oracle.query(b";admin=true");
+        assert!(!oracle.is_admin(&escaped));
+
+        let flipped = ctr_bit_flip_attack(&mut oracle, b";admin=true").unwrap();
+        assert!(oracle.is_admin(&flipped));
+
+        // Only the bytes of our input changed
+        let plaintext = oracle.decrypt(&flipped).unwrap();
+        let prefix_length = COMMENT_PREFIX.len();
+        assert_eq!(&plaintext[..prefix_length], COMMENT_PREFIX);
+        assert_eq!(&plaintext[prefix_length..prefix_length + 11], b";admin=true");
+        assert_eq!(&plaintext[prefix_length + 11..], COMMENT_SUFFIX);
+    }
+}
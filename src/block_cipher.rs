@@ -0,0 +1,20 @@
+use error::CryptoError;
+
+// A keyed permutation on fixed size blocks, which modes of operation build on. Blocks are
+// transformed in place and must be exactly block_size() bytes long.
+pub trait BlockCipher {
+    fn block_size(&self) -> usize;
+    fn encrypt_block(&self, block: &mut [u8]) -> Result<(), CryptoError>;
+    fn decrypt_block(&self, block: &mut [u8]) -> Result<(), CryptoError>;
+}
+
+// Fails unless a buffer is a whole, non-zero number of blocks.
+pub fn check_whole_blocks(block_size: usize, data: &[u8]) -> Result<(), CryptoError> {
+    if block_size == 0 {
+        return Err(CryptoError::InvalidBlockSize(block_size));
+    }
+    if data.is_empty() || !data.len().is_multiple_of(block_size) {
+        return Err(CryptoError::InvalidInputLength { block_size, actual: data.len() });
+    }
+    Ok(())
+}
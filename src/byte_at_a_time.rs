@@ -0,0 +1,135 @@
+use oracle::{Oracle, BlockMode};
+use oracle_profile::{self, OracleProfile};
+
+// Byte-at-a-time ECB decryption recovers the secret suffix of an ECB oracle. Our input is sized
+// so the next unknown byte is the last of a block whose other bytes we know. Since ECB encrypts
+// equal blocks alike, trying all 256 values for that last byte in blocks of our own finds it.
+// All 256 guesses go in a single query, so each byte costs two queries.
+
+// Filler byte for the input
+const FILL: u8 = b'A';
+
+// Recovers the suffix of a deterministic ECB oracle, with or without a fixed prefix, calling
+// progress with the bytes recovered so far after each new byte.
+pub fn recover_ecb_suffix<O, F>(oracle: &mut O, progress: F) -> Result<Vec<u8>, String>
+    where O: Oracle,
+          F: FnMut(&[u8])
+{
+    let profile = oracle_profile::profile_oracle(oracle)?;
+    recover_ecb_suffix_with_profile(oracle, &profile, progress)
+}
+
+// As recover_ecb_suffix, for an oracle already profiled.
+pub fn recover_ecb_suffix_with_profile<O, F>(oracle: &mut O,
+                                             profile: &OracleProfile,
+                                             mut progress: F)
+                                             -> Result<Vec<u8>, String>
+    where O: Oracle,
+          F: FnMut(&[u8])
+{
+    if profile.mode != BlockMode::Ecb {
+        return Err("Byte-at-a-time decryption needs an ECB oracle".to_owned());
+    }
+    let block_size = profile.block_size;
+
+    // Filler that completes the prefix's last block, so our input starts on a block boundary
+    let alignment = (block_size - profile.prefix_length % block_size) % block_size;
+    let first_block = (profile.prefix_length + alignment) / block_size;
+
+    let mut recovered: Vec<u8> = Vec::with_capacity(profile.suffix_length);
+    while recovered.len() < profile.suffix_length {
+        let position = recovered.len();
+        let pad_length = block_size - 1 - position % block_size;
+        let target_block = first_block + position / block_size;
+
+        let mut input = vec![FILL; alignment + pad_length];
+        let ciphertext = oracle.query(&input);
+        let target = block(&ciphertext, target_block, block_size)?.to_vec();
+
+        // One block per guess, each the block_size - 1 bytes before the unknown byte, taken
+        // from the filler and what is already known, then the guess
+        input.extend_from_slice(&recovered);
+        let known = &input[input.len() - (block_size - 1)..];
+        let mut probe = vec![FILL; alignment];
+        for guess in 0..256 {
+            probe.extend_from_slice(known);
+            probe.push(guess as u8);
+        }
+        let ciphertext = oracle.query(&probe);
+
+        let mut found = None;
+        for guess in 0..256 {
+            if block(&ciphertext, first_block + guess, block_size)? == &target[..] {
+                found = Some(guess as u8);
+                break;
+            }
+        }
+        match found {
+            Some(byte) => recovered.push(byte),
+            None => return Err(format!("No byte matched at suffix position {}", position)),
+        }
+        progress(&recovered);
+    }
+    Ok(recovered)
+}
+
+fn block(ciphertext: &[u8], index: usize, block_size: usize) -> Result<&[u8], String> {
+    ciphertext.get(index * block_size..(index + 1) * block_size)
+        .ok_or_else(|| "Oracle ciphertext was shorter than expected".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use aes::Aes;
+    use base64::Base64Decodable;
+    use byte_at_a_time::recover_ecb_suffix;
+    use cbc;
+    use oracle::{EcbOracle, CountingOracle};
+
+    fn challenge_12_secret() -> Vec<u8> {
+        b"Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRvd24gc28gbXkgaGFpciBjYW4gYmxvdwpUaGUg\
+          Z2lybGllcyBvbiBzdGFuZGJ5IHdhdmluZyBqdXN0IHRvIHNheSBoaQpEaWQgeW91IHN0b3A/IE5vLCBJIGp1\
+          c3QgZHJvdmUgYnkK".to_vec().from_base64_to_u8_vec().unwrap()
+    }
+
+    #[test]
+    fn challenge_12() {
+        let secret = challenge_12_secret();
+        assert!(secret.starts_with(b"Rollin' in my 5.0\n"));
+        let mut rng = StdRng::seed_from_u64(38);
+        let mut oracle = CountingOracle::new(EcbOracle::with_suffix(&secret, &mut rng));
+
+        let mut updates = Vec::new();
+        let recovered = recover_ecb_suffix(&mut oracle, |so_far| updates.push(so_far.to_vec()))
+            .unwrap();
+        assert_eq!(recovered, secret);
+        assert_eq!(updates.len(), secret.len());
+        assert_eq!(updates[0], &secret[..1]);
+        assert_eq!(updates[updates.len() - 1], secret);
+        // Two queries per byte, plus profiling
+        assert!(oracle.queries() < secret.len() * 2 + 100);
+    }
+
+    #[test]
+    fn challenge_14() {
+        let secret = challenge_12_secret();
+        let mut rng = StdRng::seed_from_u64(38);
+        for _ in 0..3 {
+            let mut oracle = EcbOracle::with_random_prefix(&secret, &mut rng);
+            assert_eq!(recover_ecb_suffix(&mut oracle, |_| {}).unwrap(), secret);
+        }
+    }
+
+    #[test]
+    fn rejects_cbc() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let mut oracle = |input: &[u8]| {
+            let mut plaintext = input.to_vec();
+            plaintext.extend_from_slice(b"secret");
+            cbc::cbc_encrypt(&aes, &[0; 16], &plaintext).unwrap()
+        };
+        assert!(recover_ecb_suffix(&mut oracle, |_| {}).is_err());
+    }
+}
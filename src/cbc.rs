@@ -0,0 +1,258 @@
+use std::io::{self, Read, Write};
+use block_cipher::{self, BlockCipher};
+use error::CryptoError;
+use fixed_xor::fixed_xor_in_place;
+use padding;
+
+// Cipher block chaining XORs each plaintext block with the previous ciphertext block, or with
+// the IV for the first, before encrypting it. Equal plaintext blocks no longer give equal
+// ciphertext, but a change to one ciphertext block flips the same bits in the next plaintext.
+
+// Blocks read at a time by the streaming functions
+const STREAM_BLOCKS: usize = 256;
+
+fn check_iv<C: BlockCipher>(cipher: &C, iv: &[u8]) -> Result<(), CryptoError> {
+    if iv.len() != cipher.block_size() {
+        return Err(CryptoError::InvalidIvLength { expected: cipher.block_size(), actual: iv.len() });
+    }
+    Ok(())
+}
+
+// Encrypts whole blocks in place, starting from and updating the previous ciphertext block.
+fn encrypt_chained<C: BlockCipher>(cipher: &C, previous: &mut [u8], data: &mut [u8])
+                                   -> Result<(), CryptoError> {
+    for block in data.chunks_mut(cipher.block_size()) {
+        fixed_xor_in_place(block, previous)?;
+        cipher.encrypt_block(block)?;
+        previous.copy_from_slice(block);
+    }
+    Ok(())
+}
+
+fn decrypt_chained<C: BlockCipher>(cipher: &C, previous: &mut [u8], data: &mut [u8])
+                                   -> Result<(), CryptoError> {
+    let mut ciphertext_block = previous.to_vec();
+    for block in data.chunks_mut(cipher.block_size()) {
+        ciphertext_block.copy_from_slice(block);
+        cipher.decrypt_block(block)?;
+        fixed_xor_in_place(block, previous)?;
+        previous.copy_from_slice(&ciphertext_block);
+    }
+    Ok(())
+}
+
+// Encrypts a buffer of whole blocks in place, without padding.
+pub fn cbc_encrypt_blocks<C: BlockCipher>(cipher: &C, iv: &[u8], data: &mut [u8])
+                                          -> Result<(), CryptoError> {
+    check_iv(cipher, iv)?;
+    block_cipher::check_whole_blocks(cipher.block_size(), data)?;
+    encrypt_chained(cipher, &mut iv.to_vec(), data)
+}
+
+// Decrypts a buffer of whole blocks in place, leaving any padding in place.
+pub fn cbc_decrypt_blocks<C: BlockCipher>(cipher: &C, iv: &[u8], data: &mut [u8])
+                                          -> Result<(), CryptoError> {
+    check_iv(cipher, iv)?;
+    block_cipher::check_whole_blocks(cipher.block_size(), data)?;
+    decrypt_chained(cipher, &mut iv.to_vec(), data)
+}
+
+// PKCS#7 pads then encrypts a message of any length.
+pub fn cbc_encrypt<C: BlockCipher>(cipher: &C, iv: &[u8], plaintext: &[u8])
+                                   -> Result<Vec<u8>, CryptoError> {
+    let mut data = padding::pkcs7_pad(plaintext, cipher.block_size())?;
+    cbc_encrypt_blocks(cipher, iv, &mut data)?;
+    Ok(data)
+}
+
+// Decrypts then strips PKCS#7 padding, failing if the padding is malformed.
+pub fn cbc_decrypt<C: BlockCipher>(cipher: &C, iv: &[u8], ciphertext: &[u8])
+                                   -> Result<Vec<u8>, CryptoError> {
+    let mut data = ciphertext.to_vec();
+    cbc_decrypt_blocks(cipher, iv, &mut data)?;
+    let length = padding::pkcs7_unpad(&data, cipher.block_size())?.len();
+    data.truncate(length);
+    Ok(data)
+}
+
+fn invalid_data(error: CryptoError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+// Reads until the buffer is full or the reader is exhausted, returning the bytes read.
+fn read_up_to<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+// Streaming equivalent of cbc_encrypt, holding only a fixed number of blocks in memory.
+pub fn cbc_encrypt_stream<C, R, W>(cipher: &C, iv: &[u8], reader: &mut R, writer: &mut W)
+                                   -> io::Result<()>
+    where C: BlockCipher,
+          R: Read,
+          W: Write
+{
+    check_iv(cipher, iv).map_err(invalid_data)?;
+    let block_size = cipher.block_size();
+    let mut previous = iv.to_vec();
+    let mut buffer = vec![0u8; STREAM_BLOCKS * block_size];
+    loop {
+        let read = read_up_to(reader, &mut buffer)?;
+        if read < buffer.len() {
+            let mut last = padding::pkcs7_pad(&buffer[..read], block_size).map_err(invalid_data)?;
+            encrypt_chained(cipher, &mut previous, &mut last).map_err(invalid_data)?;
+            return writer.write_all(&last);
+        }
+        encrypt_chained(cipher, &mut previous, &mut buffer).map_err(invalid_data)?;
+        writer.write_all(&buffer)?;
+    }
+}
+
+// Streaming equivalent of cbc_decrypt. The last plaintext block is held back until the end of
+// the input, as only then is it known to carry the padding.
+pub fn cbc_decrypt_stream<C, R, W>(cipher: &C, iv: &[u8], reader: &mut R, writer: &mut W)
+                                   -> io::Result<()>
+    where C: BlockCipher,
+          R: Read,
+          W: Write
+{
+    check_iv(cipher, iv).map_err(invalid_data)?;
+    let block_size = cipher.block_size();
+    let mut previous = iv.to_vec();
+    let mut buffer = vec![0u8; STREAM_BLOCKS * block_size];
+    let mut held = Vec::new();
+    let mut total = 0;
+    loop {
+        let read = read_up_to(reader, &mut buffer)?;
+        total += read;
+        if !read.is_multiple_of(block_size) {
+            return Err(invalid_data(CryptoError::InvalidInputLength { block_size, actual: total }));
+        }
+        if read > 0 {
+            let data = &mut buffer[..read];
+            decrypt_chained(cipher, &mut previous, data).map_err(invalid_data)?;
+            writer.write_all(&held)?;
+            let (body, last) = data.split_at(read - block_size);
+            writer.write_all(body)?;
+            held = last.to_vec();
+        }
+        if read < buffer.len() {
+            if held.is_empty() {
+                return Err(invalid_data(CryptoError::InvalidInputLength { block_size, actual: 0 }));
+            }
+            let unpadded = padding::pkcs7_unpad(&held, block_size).map_err(invalid_data)?;
+            return writer.write_all(unpadded);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use aes::Aes;
+    use base64::Base64Decodable;
+    use cbc::{cbc_encrypt, cbc_decrypt, cbc_encrypt_blocks, cbc_decrypt_blocks,
+              cbc_encrypt_stream, cbc_decrypt_stream, STREAM_BLOCKS};
+    use ecb::ecb_encrypt;
+    use error::CryptoError;
+    use rustc_serialize::hex::FromHex;
+
+    const NIST_PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                                  30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+    const NIST_IV: &str = "000102030405060708090a0b0c0d0e0f";
+
+    fn check_nist_vector(key: &str, ciphertext: &str) {
+        let aes = Aes::new(&key.from_hex().unwrap()).unwrap();
+        let iv = NIST_IV.from_hex().unwrap();
+        let plaintext = NIST_PLAINTEXT.from_hex().unwrap();
+
+        let mut data = plaintext.clone();
+        cbc_encrypt_blocks(&aes, &iv, &mut data).unwrap();
+        assert_eq!(data, ciphertext.from_hex().unwrap());
+        cbc_decrypt_blocks(&aes, &iv, &mut data).unwrap();
+        assert_eq!(data, plaintext);
+    }
+
+    #[test]
+    fn nist_sp_800_38a_cbc() {
+        // F.2.1 and F.2.5
+        check_nist_vector("2b7e151628aed2a6abf7158809cf4f3c",
+                          "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2\
+                           73bed6b8e3c1743b7116e69e222295163ff1caa1681fac09120eca307586e1a7");
+        check_nist_vector("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+                          "f58c4c04d6e5f1ba779eabfb5f7bfbd69cfc4e967edb808d679f777bc6702c7d\
+                           39f23369a9d9bacfa530e26304231461b2eb05e2c39be9fcda6c19078c6a9d1b");
+    }
+
+    #[test]
+    fn challenge_10() {
+        // Opening blocks of the challenge data
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let mut data = b"CRIwqt4+szDbqkNY+I0qbNXPg1XLaCM5etQ5Bt9DRFU=".to_vec()
+            .from_base64_to_u8_vec().unwrap();
+        cbc_decrypt_blocks(&aes, &[0u8; 16], &mut data).unwrap();
+        assert_eq!(data, b"I'm back and I'm ringin' the bell \nA rockin'"[..32].to_vec());
+    }
+
+    #[test]
+    fn padded_round_trip() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let iv = b"0123456789abcdef";
+        for length in 0..40 {
+            let plaintext: Vec<u8> = (0..length).map(|i| i as u8).collect();
+            let ciphertext = cbc_encrypt(&aes, iv, &plaintext).unwrap();
+            assert_eq!(ciphertext.len(), (length / 16 + 1) * 16);
+            assert_eq!(cbc_decrypt(&aes, iv, &ciphertext).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn equal_blocks_hidden() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let ciphertext = cbc_encrypt(&aes, &[0u8; 16], &[b'A'; 32]).unwrap();
+        assert!(ciphertext[0..16] != ciphertext[16..32]);
+        // With a zero IV the first block is the same as under ECB
+        assert_eq!(ciphertext[0..16], ecb_encrypt(&aes, &[b'A'; 32]).unwrap()[0..16]);
+    }
+
+    #[test]
+    fn streaming_matches_buffered() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let iv = b"0123456789abcdef";
+        let chunk = STREAM_BLOCKS * 16;
+        for &length in &[0, 5, 16, chunk - 1, chunk, chunk + 16, 3 * chunk + 7] {
+            let plaintext: Vec<u8> = (0..length).map(|i| (i * 7) as u8).collect();
+            let mut ciphertext = Vec::new();
+            cbc_encrypt_stream(&aes, iv, &mut &plaintext[..], &mut ciphertext).unwrap();
+            assert_eq!(ciphertext, cbc_encrypt(&aes, iv, &plaintext).unwrap());
+
+            let mut decrypted = Vec::new();
+            cbc_decrypt_stream(&aes, iv, &mut &ciphertext[..], &mut decrypted).unwrap();
+            assert_eq!(decrypted, plaintext);
+        }
+    }
+
+    #[test]
+    fn invalid_input() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        assert_eq!(cbc_encrypt(&aes, &[0u8; 8], b"hello"),
+                   Err(CryptoError::InvalidIvLength { expected: 16, actual: 8 }));
+        assert_eq!(cbc_decrypt(&aes, &[0u8; 16], &[0u8; 20]),
+                   Err(CryptoError::InvalidInputLength { block_size: 16, actual: 20 }));
+
+        let mut output = Vec::new();
+        let error = cbc_decrypt_stream(&aes, &[0u8; 16], &mut &[0u8; 20][..], &mut output)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        let error = cbc_decrypt_stream(&aes, &[0u8; 16], &mut &[][..], &mut output).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,353 @@
+use std::cmp::Ordering;
+use std::collections::btree_map::BTreeMap;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use frequency_analysis::{self, FrequencyAnalysable, NgramModel};
+use utility;
+
+// Classical ciphers over the 26 letter English alphabet. Letters keep their case and every other
+// character passes through unchanged.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassicalCandidate<K> {
+    pub key: K,
+    pub plaintext: String,
+    // N-gram model score of the plaintext, higher is better
+    pub score: f64,
+}
+
+fn sort_candidates<K>(candidates: &mut [ClassicalCandidate<K>]) {
+    candidates.sort_by(|c1, c2| c2.score.partial_cmp(&c1.score).unwrap_or(Ordering::Equal));
+}
+
+// Applies f to the 0-25 index of every letter, preserving case. f is also given the index of
+// the letter among the letters of the text.
+fn map_letters<F>(text: &str, mut f: F) -> String
+    where F: FnMut(u8, usize) -> u8 {
+    let mut letter_index = 0;
+    text.chars()
+        .map(|c| {
+            if !c.is_ascii_alphabetic() {
+                return c;
+            }
+            let base = if c.is_ascii_uppercase() { b'A' } else { b'a' };
+            let mapped = f(c as u8 - base, letter_index) % 26;
+            letter_index += 1;
+            (base + mapped) as char
+        })
+        .collect()
+}
+
+fn letter_indices(text: &str) -> Vec<u8> {
+    text.bytes()
+        .filter(|b| b.is_ascii_alphabetic())
+        .map(|b| b.to_ascii_lowercase() - b'a')
+        .collect()
+}
+
+fn key_letter_indices(key: &str) -> Result<Vec<u8>, String> {
+    if key.is_empty() || !key.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return Err(format!("Key must be a non-empty string of letters, got: {:?}", key));
+    }
+    Ok(letter_indices(key))
+}
+
+pub fn caesar_encrypt(plaintext: &str, shift: u8) -> String {
+    map_letters(plaintext, |l, _| l + shift % 26)
+}
+
+pub fn caesar_decrypt(ciphertext: &str, shift: u8) -> String {
+    map_letters(ciphertext, |l, _| l + 26 - shift % 26)
+}
+
+pub fn rot13(text: &str) -> String {
+    caesar_encrypt(text, 13)
+}
+
+// Tries every shift, best first.
+pub fn crack_caesar(ciphertext: &str, model: &NgramModel) -> Vec<ClassicalCandidate<u8>> {
+    let mut candidates: Vec<ClassicalCandidate<u8>> = (0..26)
+        .map(|shift| {
+            let plaintext = caesar_decrypt(ciphertext, shift);
+            let score = model.score(plaintext.as_bytes());
+            ClassicalCandidate { key: shift, plaintext, score }
+        })
+        .collect();
+    sort_candidates(&mut candidates);
+    candidates
+}
+
+pub fn vigenere_encrypt(plaintext: &str, key: &str) -> Result<String, String> {
+    let key = key_letter_indices(key)?;
+    Ok(map_letters(plaintext, |l, i| l + key[i % key.len()]))
+}
+
+pub fn vigenere_decrypt(ciphertext: &str, key: &str) -> Result<String, String> {
+    let key = key_letter_indices(key)?;
+    Ok(map_letters(ciphertext, |l, i| l + 26 - key[i % key.len()]))
+}
+
+// Ranks likely Vigenère key lengths using the same estimators as repeating XOR, over the
+// letters of the ciphertext.
+pub fn find_vigenere_key_lengths(ciphertext: &str,
+                                 min_key_length: usize,
+                                 max_key_length: usize)
+                                 -> Result<Vec<usize>, String> {
+    let letters = letter_indices(ciphertext);
+    let coincidences =
+        utility::find_coincidence_key_lengths(&letters, min_key_length, max_key_length)?;
+    let kasiski = utility::find_kasiski_key_lengths(&letters, min_key_length, max_key_length);
+    let mut candidates = utility::rank_key_lengths(&[
+        coincidences.into_iter().map(|(_, l)| l).collect(),
+        kasiski.into_iter().map(|(_, l)| l).collect(),
+    ]);
+    candidates.retain(|&l| letters.len() / l >= 2);
+    Ok(candidates)
+}
+
+// Number of most likely key lengths to solve for when cracking Vigenère
+const VIGENERE_KEY_LENGTHS_TO_TRY: usize = 5;
+
+// Solves each column of the most likely key lengths as a Caesar cipher by letter frequency, then
+// ranks the resulting keys by n-gram score.
+pub fn crack_vigenere(ciphertext: &str,
+                      min_key_length: usize,
+                      max_key_length: usize,
+                      character_frequencies: &BTreeMap<char, f32>,
+                      model: &NgramModel)
+                      -> Result<Vec<ClassicalCandidate<String>>, String> {
+    let letters = letter_indices(ciphertext);
+    let key_lengths = find_vigenere_key_lengths(ciphertext, min_key_length, max_key_length)?;
+    let mut candidates: Vec<ClassicalCandidate<String>> = Vec::new();
+    for &key_length in key_lengths.iter().take(VIGENERE_KEY_LENGTHS_TO_TRY) {
+        let mut key = String::with_capacity(key_length);
+        for offset in 0..key_length {
+            let column: Vec<u8> = letters[offset..].iter().step_by(key_length).cloned().collect();
+            let best_shift = (0..26u8)
+                .map(|shift| {
+                    let decoded: String =
+                        column.iter().map(|l| (b'a' + (l + 26 - shift) % 26) as char).collect();
+                    let score = frequency_analysis::character_frequency_score(decoded.chars(),
+                                                                              character_frequencies);
+                    (score, shift)
+                })
+                .max_by(|&(s1, _), &(s2, _)| s1.partial_cmp(&s2).unwrap_or(Ordering::Equal))
+                .map(|(_, shift)| shift)
+                .unwrap_or(0);
+            key.push((b'A' + best_shift) as char);
+        }
+        if candidates.iter().any(|c| c.key == key) {
+            continue;
+        }
+        let plaintext = vigenere_decrypt(ciphertext, &key)?;
+        let score = model.score(plaintext.as_bytes());
+        candidates.push(ClassicalCandidate { key, plaintext, score });
+    }
+    sort_candidates(&mut candidates);
+    Ok(candidates)
+}
+
+fn modular_inverse_26(a: u8) -> Option<u8> {
+    (1..26u8).find(|&x| (a as u32 * x as u32) % 26 == 1)
+}
+
+// E(x) = (a * x + b) mod 26, where a must be coprime with 26.
+pub fn affine_encrypt(plaintext: &str, a: u8, b: u8) -> Result<String, String> {
+    if modular_inverse_26(a % 26).is_none() {
+        return Err(format!("Affine multiplier {} is not coprime with 26", a));
+    }
+    Ok(map_letters(plaintext, |l, _| ((a as u32 * l as u32 + b as u32) % 26) as u8))
+}
+
+pub fn affine_decrypt(ciphertext: &str, a: u8, b: u8) -> Result<String, String> {
+    let a_inverse = match modular_inverse_26(a % 26) {
+        Some(inverse) => inverse as u32,
+        None => return Err(format!("Affine multiplier {} is not coprime with 26", a)),
+    };
+    Ok(map_letters(ciphertext, |l, _| ((a_inverse * (l as u32 + 26 - b as u32 % 26)) % 26) as u8))
+}
+
+// Tries all 312 (a, b) keys, best first.
+pub fn crack_affine(ciphertext: &str, model: &NgramModel) -> Vec<ClassicalCandidate<(u8, u8)>> {
+    let mut candidates = Vec::new();
+    for a in (1..26u8).filter(|&a| modular_inverse_26(a).is_some()) {
+        for b in 0..26u8 {
+            if let Ok(plaintext) = affine_decrypt(ciphertext, a, b) {
+                let score = model.score(plaintext.as_bytes());
+                candidates.push(ClassicalCandidate { key: (a, b), plaintext, score });
+            }
+        }
+    }
+    sort_candidates(&mut candidates);
+    candidates
+}
+
+// Substitution keys are given as the 26 ciphertext letters that a to z encrypt to.
+fn substitution_key_indices(key: &str) -> Result<Vec<u8>, String> {
+    let indices = key_letter_indices(key)?;
+    let mut seen = [false; 26];
+    for &i in &indices {
+        seen[i as usize] = true;
+    }
+    if indices.len() != 26 || !seen.iter().all(|&s| s) {
+        return Err(format!("Substitution key must be a permutation of the alphabet, got: {:?}", key));
+    }
+    Ok(indices)
+}
+
+pub fn substitution_encrypt(plaintext: &str, key: &str) -> Result<String, String> {
+    let key = substitution_key_indices(key)?;
+    Ok(map_letters(plaintext, |l, _| key[l as usize]))
+}
+
+pub fn substitution_decrypt(ciphertext: &str, key: &str) -> Result<String, String> {
+    let key = substitution_key_indices(key)?;
+    let mut inverse = [0u8; 26];
+    for (plain, &cipher) in key.iter().enumerate() {
+        inverse[cipher as usize] = plain as u8;
+    }
+    Ok(map_letters(ciphertext, |l, _| inverse[l as usize]))
+}
+
+// Decrypts ciphertext letter indices with a decryption key mapping cipher to plain indices.
+fn decrypt_indices(letters: &[u8], decryption_key: &[u8; 26]) -> Vec<u8> {
+    letters.iter().map(|&l| b'A' + decryption_key[l as usize]).collect()
+}
+
+// Swaps pairs of key letters while doing so improves the score, returning the final score.
+fn hill_climb(letters: &[u8], decryption_key: &mut [u8; 26], model: &NgramModel) -> f64 {
+    let mut best_score = model.score(&decrypt_indices(letters, decryption_key));
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 0..26 {
+            for j in i + 1..26 {
+                decryption_key.swap(i, j);
+                let score = model.score(&decrypt_indices(letters, decryption_key));
+                if score > best_score {
+                    best_score = score;
+                    improved = true;
+                } else {
+                    decryption_key.swap(i, j);
+                }
+            }
+        }
+    }
+    best_score
+}
+
+// Solves a monoalphabetic substitution by hill climbing on n-gram score. The first climb starts
+// from the key that matches ciphertext and English letter frequency order, and each restart
+// from a random key. Returns the best key found in substitution_encrypt's format.
+pub fn crack_substitution<R: Rng>(ciphertext: &str,
+                                  model: &NgramModel,
+                                  restarts: usize,
+                                  rng: &mut R)
+                                  -> Result<ClassicalCandidate<String>, String> {
+    let letters = letter_indices(ciphertext);
+    if letters.len() < model.n() {
+        return Err("Too few letters in ciphertext to solve substitution".to_owned());
+    }
+
+    let mut english_order: Vec<(f32, u8)> = frequency_analysis::english_letter_frequencies()
+        .into_iter()
+        .map(|(c, f)| (f, c as u8 - b'a'))
+        .collect();
+    english_order.sort_by(|&(f1, _), &(f2, _)| f2.partial_cmp(&f1).unwrap_or(Ordering::Equal));
+    let cipher_occurrences = letters.iter().occurrences();
+    let mut cipher_order: Vec<u8> = (0..26).collect();
+    cipher_order.sort_by_key(|l| ::std::cmp::Reverse(cipher_occurrences.get(l).cloned().unwrap_or(0)));
+
+    let mut decryption_key = [0u8; 26];
+    for (&cipher, &(_, plain)) in cipher_order.iter().zip(english_order.iter()) {
+        decryption_key[cipher as usize] = plain;
+    }
+
+    let mut best_score = hill_climb(&letters, &mut decryption_key, model);
+    let mut best_key = decryption_key;
+    for _ in 0..restarts {
+        decryption_key.shuffle(rng);
+        let score = hill_climb(&letters, &mut decryption_key, model);
+        if score > best_score {
+            best_score = score;
+            best_key = decryption_key;
+        }
+    }
+
+    let mut key = [b'a'; 26];
+    for (cipher, &plain) in best_key.iter().enumerate() {
+        key[plain as usize] = b'a' + cipher as u8;
+    }
+    let key: String = key.iter().map(|&b| b as char).collect();
+    let plaintext = substitution_decrypt(ciphertext, &key)?;
+    Ok(ClassicalCandidate { key, plaintext, score: best_score })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use classical::{caesar_encrypt, caesar_decrypt, rot13, crack_caesar, vigenere_encrypt,
+                    vigenere_decrypt, find_vigenere_key_lengths, crack_vigenere, affine_encrypt,
+                    affine_decrypt, crack_affine, substitution_encrypt, substitution_decrypt,
+                    crack_substitution};
+    use frequency_analysis::{english_letter_frequencies, NgramModel};
+
+    static PLAINTEXT: &str = "When the storm finally passed, the fishermen went down to the harbour to count their \
+        boats. Two had been torn from their moorings and thrown against the rocks, but the rest were only \
+        scratched. By noon the nets were mended and the first of the boats were heading out past the lighthouse, \
+        because there was no sense in wasting a calm sea after a week of wind.";
+
+    #[test]
+    fn caesar() {
+        assert_eq!(caesar_encrypt("Hello, World!", 3), "Khoor, Zruog!");
+        assert_eq!(caesar_decrypt("Khoor, Zruog!", 3), "Hello, World!");
+        assert_eq!(rot13(&rot13("Hello")), "Hello");
+        assert_eq!(rot13("Hello"), "Uryyb");
+
+        let candidates = crack_caesar(&caesar_encrypt(PLAINTEXT, 17), &NgramModel::english_trigrams());
+        assert_eq!(candidates.len(), 26);
+        assert_eq!(candidates[0].key, 17);
+        assert_eq!(candidates[0].plaintext, PLAINTEXT);
+    }
+
+    #[test]
+    fn vigenere() {
+        assert_eq!(vigenere_encrypt("ATTACK AT DAWN", "LEMON").unwrap(), "LXFOPV EF RNHR");
+        assert_eq!(vigenere_decrypt("LXFOPV EF RNHR", "lemon").unwrap(), "ATTACK AT DAWN");
+        assert!(vigenere_encrypt("ATTACK", "L3MON").is_err());
+        assert!(vigenere_encrypt("ATTACK", "").is_err());
+
+        let ciphertext = vigenere_encrypt(PLAINTEXT, "FLOOD").unwrap();
+        assert!(find_vigenere_key_lengths(&ciphertext, 1, 20).unwrap()[..3].contains(&5));
+        let candidates = crack_vigenere(&ciphertext, 1, 20, &english_letter_frequencies(),
+                                        &NgramModel::english_trigrams()).unwrap();
+        assert_eq!(candidates[0].key, "FLOOD");
+        assert_eq!(candidates[0].plaintext, PLAINTEXT);
+    }
+
+    #[test]
+    fn affine() {
+        assert_eq!(affine_encrypt("AFFINE cipher", 5, 8).unwrap(), "IHHWVC swfrcp");
+        assert_eq!(affine_decrypt("IHHWVC swfrcp", 5, 8).unwrap(), "AFFINE cipher");
+        assert!(affine_encrypt("AFFINE", 13, 8).is_err());
+
+        let candidates = crack_affine(&affine_encrypt(PLAINTEXT, 7, 3).unwrap(), &NgramModel::english_trigrams());
+        assert_eq!(candidates.len(), 312);
+        assert_eq!(candidates[0].key, (7, 3));
+        assert_eq!(candidates[0].plaintext, PLAINTEXT);
+    }
+
+    #[test]
+    fn substitution() {
+        let key = "qwertyuiopasdfghjklzxcvbnm";
+        assert_eq!(substitution_encrypt("Hello", key).unwrap(), "Itssg");
+        assert_eq!(substitution_decrypt("Itssg", key).unwrap(), "Hello");
+        assert!(substitution_encrypt("Hello", "qwertyuiopasdfghjklzxcvbnq").is_err());
+
+        let ciphertext = substitution_encrypt(PLAINTEXT, key).unwrap();
+        let mut rng = StdRng::seed_from_u64(28);
+        let candidate = crack_substitution(&ciphertext, &NgramModel::english_trigrams(), 5, &mut rng).unwrap();
+        assert_eq!(candidate.plaintext, PLAINTEXT);
+    }
+}
@@ -0,0 +1,195 @@
+use block_cipher::BlockCipher;
+use error::CryptoError;
+use fixed_xor::fixed_xor_in_place;
+use stream_cipher::StreamCipher;
+
+// Counter mode turns a block cipher into a stream cipher: the keystream is the encryption of
+// successive counter blocks, and encryption and decryption both XOR it with the data. Block n
+// of the keystream depends only on n, so any part of it can be generated directly.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+// How a counter block is laid out: a fixed nonce, then a counter that wraps within its width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CounterLayout {
+    pub nonce_length: usize,
+    pub counter_width: usize,
+    pub endianness: Endianness,
+}
+
+impl CounterLayout {
+    // Cryptopals: 64 bit little endian nonce, then 64 bit little endian block count
+    pub fn cryptopals() -> CounterLayout {
+        CounterLayout { nonce_length: 8, counter_width: 8, endianness: Endianness::Little }
+    }
+
+    // NIST SP 800-38A: the whole 128 bit block as one big endian counter
+    pub fn nist() -> CounterLayout {
+        CounterLayout { nonce_length: 0, counter_width: 16, endianness: Endianness::Big }
+    }
+}
+
+pub struct Ctr<C: BlockCipher> {
+    cipher: C,
+    layout: CounterLayout,
+    nonce: Vec<u8>,
+    initial_counter: u128,
+}
+
+impl<C: BlockCipher> Ctr<C> {
+    // The layout must fill the cipher's block exactly, with a counter of at most 16 bytes.
+    pub fn new(cipher: C, layout: CounterLayout, nonce: &[u8], initial_counter: u128)
+               -> Result<Ctr<C>, CryptoError> {
+        let block_size = cipher.block_size();
+        if layout.counter_width == 0 || layout.counter_width > 16 ||
+           layout.nonce_length + layout.counter_width != block_size {
+            return Err(CryptoError::InvalidBlockSize(block_size));
+        }
+        if nonce.len() != layout.nonce_length {
+            return Err(CryptoError::InvalidIvLength {
+                expected: layout.nonce_length,
+                actual: nonce.len(),
+            });
+        }
+        Ok(Ctr { cipher, layout, nonce: nonce.to_vec(), initial_counter })
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.cipher.block_size()
+    }
+
+    fn counter_block(&self, index: u128) -> Vec<u8> {
+        let width = self.layout.counter_width;
+        let mut counter = self.initial_counter.wrapping_add(index);
+        if width < 16 {
+            counter &= (1 << (8 * width)) - 1;
+        }
+
+        let mut block = self.nonce.clone();
+        match self.layout.endianness {
+            Endianness::Little => block.extend_from_slice(&counter.to_le_bytes()[..width]),
+            Endianness::Big => block.extend_from_slice(&counter.to_be_bytes()[16 - width..]),
+        }
+        block
+    }
+
+    // Keystream block number index.
+    pub fn keystream_block(&self, index: u128) -> Result<Vec<u8>, CryptoError> {
+        let mut block = self.counter_block(index);
+        self.cipher.encrypt_block(&mut block)?;
+        Ok(block)
+    }
+
+    // length bytes of keystream starting offset bytes in.
+    pub fn keystream(&self, offset: u64, length: usize) -> Result<Vec<u8>, CryptoError> {
+        let block_size = self.block_size() as u64;
+        let skip = (offset % block_size) as usize;
+        let mut keystream = Vec::with_capacity(skip + length);
+        let mut index = (offset / block_size) as u128;
+        while keystream.len() < skip + length {
+            keystream.extend(self.keystream_block(index)?);
+            index += 1;
+        }
+        keystream.truncate(skip + length);
+        Ok(keystream.split_off(skip))
+    }
+}
+
+impl<C: BlockCipher> StreamCipher for Ctr<C> {
+    fn apply_keystream(&self, offset: u64, data: &mut [u8]) -> Result<(), CryptoError> {
+        let keystream = self.keystream(offset, data.len())?;
+        fixed_xor_in_place(data, &keystream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aes::Aes;
+    use base64::Base64Decodable;
+    use ctr::{Ctr, CounterLayout, Endianness};
+    use error::CryptoError;
+    use rustc_serialize::hex::FromHex;
+    use stream_cipher::StreamCipher;
+
+    fn nist_ctr(key: &str) -> Ctr<Aes> {
+        let aes = Aes::new(&key.from_hex().unwrap()).unwrap();
+        Ctr::new(aes, CounterLayout::nist(), &[], 0xf0f1f2f3f4f5f6f7f8f9fafbfcfdfeff).unwrap()
+    }
+
+    #[test]
+    fn nist_sp_800_38a_ctr() {
+        // F.5.1 and F.5.5
+        let plaintext = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710"
+            .from_hex().unwrap();
+        let ctr = nist_ctr("2b7e151628aed2a6abf7158809cf4f3c");
+        let ciphertext = ctr.process(&plaintext).unwrap();
+        assert_eq!(ciphertext,
+                   "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+                    5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee"
+                       .from_hex().unwrap());
+        assert_eq!(ctr.process(&ciphertext).unwrap(), plaintext);
+
+        let ctr = nist_ctr("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4");
+        assert_eq!(ctr.process(&plaintext).unwrap(),
+                   "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5\
+                    2b0930daa23de94ce87017ba2d84988ddfc9c58db67aada613c2dd08457941a6"
+                       .from_hex().unwrap());
+    }
+
+    #[test]
+    fn challenge_18() {
+        let ciphertext = b"L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ=="
+            .to_vec().from_base64_to_u8_vec().unwrap();
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let ctr = Ctr::new(aes, CounterLayout::cryptopals(), &[0; 8], 0).unwrap();
+        assert_eq!(ctr.process(&ciphertext).unwrap(),
+                   &b"Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby "[..]);
+    }
+
+    #[test]
+    fn counter_layouts() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let ctr = Ctr::new(aes.clone(), CounterLayout::cryptopals(), b"noncenon", 0x0102).unwrap();
+        assert_eq!(ctr.counter_block(0), b"noncenon\x02\x01\x00\x00\x00\x00\x00\x00");
+        assert_eq!(ctr.counter_block(0xff), b"noncenon\x01\x02\x00\x00\x00\x00\x00\x00");
+
+        // A 32 bit counter wraps without carrying into the nonce
+        let layout = CounterLayout { nonce_length: 12, counter_width: 4, endianness: Endianness::Big };
+        let ctr = Ctr::new(aes.clone(), layout, &[0xaa; 12], 0xffff_ffff).unwrap();
+        assert_eq!(ctr.counter_block(0)[12..], [0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(ctr.counter_block(1), [vec![0xaa; 12], vec![0; 4]].concat());
+
+        let ctr = Ctr::new(aes.clone(), CounterLayout::nist(), &[], u128::MAX).unwrap();
+        assert_eq!(ctr.counter_block(1), vec![0; 16]);
+    }
+
+    #[test]
+    fn seekable_keystream() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let ctr = Ctr::new(aes, CounterLayout::cryptopals(), &[3; 8], 0).unwrap();
+        let full = ctr.keystream(0, 100).unwrap();
+        for &(offset, length) in &[(0, 0), (5, 11), (16, 16), (20, 30), (47, 53)] {
+            assert_eq!(ctr.keystream(offset as u64, length).unwrap(), &full[offset..offset + length]);
+        }
+
+        let plaintext: Vec<u8> = (0..100).collect();
+        let ciphertext = ctr.process(&plaintext).unwrap();
+        let mut middle = ciphertext[37..61].to_vec();
+        ctr.apply_keystream(37, &mut middle).unwrap();
+        assert_eq!(middle, &plaintext[37..61]);
+    }
+
+    #[test]
+    fn invalid_layouts() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        assert_eq!(Ctr::new(aes.clone(), CounterLayout::cryptopals(), &[0; 4], 0).err(),
+                   Some(CryptoError::InvalidIvLength { expected: 8, actual: 4 }));
+        let layout = CounterLayout { nonce_length: 4, counter_width: 8, endianness: Endianness::Big };
+        assert_eq!(Ctr::new(aes, layout, &[0; 4], 0).err(), Some(CryptoError::InvalidBlockSize(16)));
+    }
+}
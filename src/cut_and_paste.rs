@@ -0,0 +1,143 @@
+use std::ops::Range;
+use rand::Rng;
+use aes::Aes;
+use ecb;
+use kv;
+use oracle::{self, Oracle, BlockMode};
+use oracle_profile;
+use padding;
+
+// ECB cut-and-paste: since every block is encrypted on its own, blocks from the ciphertexts of
+// different queries can be reassembled into a ciphertext for a message the oracle never saw.
+
+// Concatenates ranges of blocks taken from any number of ciphertexts.
+pub fn splice_blocks(block_size: usize, pieces: &[(&[u8], Range<usize>)]) -> Result<Vec<u8>, String> {
+    let mut spliced = Vec::new();
+    for (ciphertext, blocks) in pieces {
+        let bytes = ciphertext.get(blocks.start * block_size..blocks.end * block_size)
+            .ok_or_else(|| format!("Blocks {:?} are outside a ciphertext of {} bytes",
+                                   blocks, ciphertext.len()))?;
+        spliced.extend_from_slice(bytes);
+    }
+    Ok(spliced)
+}
+
+// Challenge 13: encrypts the cookie for a user profile under AES-ECB with a fixed random key.
+pub struct ProfileOracle {
+    aes: Aes,
+}
+
+impl ProfileOracle {
+    pub fn new<R: Rng>(rng: &mut R) -> ProfileOracle {
+        let key = oracle::random_bytes(rng, 16);
+        ProfileOracle { aes: Aes::new(&key).expect("AES accepts a 16 byte key") }
+    }
+
+    pub fn profile_for(email: &str) -> String {
+        kv::encode_kv(&[("email", email), ("uid", "10"), ("role", "user")])
+    }
+
+    pub fn decrypt_profile(&self, ciphertext: &[u8]) -> Result<Vec<(String, String)>, String> {
+        let cookie = ecb::ecb_decrypt(&self.aes, ciphertext)?;
+        kv::parse_kv(&String::from_utf8(cookie).map_err(|e| e.to_string())?)
+    }
+
+    pub fn is_admin(&self, ciphertext: &[u8]) -> bool {
+        self.decrypt_profile(ciphertext)
+            .map(|profile| kv::kv_lookup(&profile, "role") == Some("admin"))
+            .unwrap_or(false)
+    }
+}
+
+impl Oracle for ProfileOracle {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        let cookie = ProfileOracle::profile_for(&String::from_utf8_lossy(input));
+        ecb::ecb_encrypt(&self.aes, cookie.as_bytes()).expect("padded input is whole blocks")
+    }
+}
+
+// Replaces the last replaced_length bytes of an ECB oracle's suffix with new_value. One query
+// puts new_value, padded, in a block of its own; another sizes the input so the bytes to be
+// replaced start a block, and the two are spliced together. Input must reach the oracle
+// unchanged, which bytes like 'A' and padding do even through escaping.
+pub fn forge_trailing_value<O: Oracle>(oracle: &mut O,
+                                       replaced_length: usize,
+                                       new_value: &[u8])
+                                       -> Result<Vec<u8>, String> {
+    let profile = oracle_profile::profile_oracle(oracle)?;
+    if profile.mode != BlockMode::Ecb {
+        return Err("Cut-and-paste needs an ECB oracle".to_owned());
+    }
+    if replaced_length > profile.suffix_length {
+        return Err("Cannot replace more than the oracle's suffix".to_owned());
+    }
+    let block_size = profile.block_size;
+    if new_value.len() >= block_size {
+        return Err(format!("New value must be shorter than a {} byte block", block_size));
+    }
+
+    // A block holding only new_value and its padding
+    let alignment = (block_size - profile.prefix_length % block_size) % block_size;
+    let mut input = vec![b'A'; alignment];
+    input.extend(padding::pkcs7_pad(new_value, block_size)?);
+    let value_block = (profile.prefix_length + alignment) / block_size;
+    let value_ciphertext = oracle.query(&input);
+
+    // Everything before the replaced bytes, ending on a block boundary
+    let kept_length = profile.prefix_length + profile.suffix_length - replaced_length;
+    let filler = (block_size - kept_length % block_size) % block_size;
+    let kept_ciphertext = oracle.query(&vec![b'A'; filler]);
+    let kept_blocks = (kept_length + filler) / block_size;
+
+    splice_blocks(block_size,
+                  &[(&kept_ciphertext, 0..kept_blocks),
+                    (&value_ciphertext, value_block..value_block + 1)])
+}
+
+// Challenge 13: turns a profile oracle's role=user into role=admin.
+pub fn forge_admin_profile<O: Oracle>(oracle: &mut O) -> Result<Vec<u8>, String> {
+    forge_trailing_value(oracle, "user".len(), b"admin")
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use cut_and_paste::{splice_blocks, ProfileOracle, forge_admin_profile};
+    use kv;
+    use oracle::Oracle;
+
+    #[test]
+    fn splice() {
+        let a = b"aaaabbbbcccc";
+        let b = b"ddddeeee";
+        assert_eq!(splice_blocks(4, &[(a, 1..3), (b, 0..1), (a, 0..1)]).unwrap(), b"bbbbccccddddaaaa");
+        assert_eq!(splice_blocks(4, &[]).unwrap(), b"");
+        assert!(splice_blocks(4, &[(b, 1..3)]).is_err());
+    }
+
+    #[test]
+    fn profile_oracle_escapes() {
+        let mut rng = StdRng::seed_from_u64(39);
+        let mut oracle = ProfileOracle::new(&mut rng);
+        assert_eq!(ProfileOracle::profile_for("foo@bar.com"), "email=foo@bar.com&uid=10&role=user");
+
+        let ciphertext = oracle.query(b"foo@bar.com&role=admin");
+        let profile = oracle.decrypt_profile(&ciphertext).unwrap();
+        assert_eq!(profile.len(), 3);
+        assert_eq!(kv::kv_lookup(&profile, "email"), Some("foo@bar.com&role=admin"));
+        assert!(!oracle.is_admin(&ciphertext));
+    }
+
+    #[test]
+    fn challenge_13() {
+        let mut rng = StdRng::seed_from_u64(39);
+        let mut oracle = ProfileOracle::new(&mut rng);
+        let forged = forge_admin_profile(&mut oracle).unwrap();
+        assert!(oracle.is_admin(&forged));
+
+        let profile = oracle.decrypt_profile(&forged).unwrap();
+        assert_eq!(kv::kv_lookup(&profile, "uid"), Some("10"));
+        assert_eq!(profile.len(), 3);
+    }
+}
@@ -0,0 +1,229 @@
+use std::cmp::Ordering;
+use block_cipher::{self, BlockCipher};
+use error::CryptoError;
+use frequency_analysis::FrequencyAnalysable;
+use padding;
+
+// Electronic codebook mode encrypts every block independently with the same key, so equal
+// plaintext blocks give equal ciphertext blocks. That leak is what the ECB attacks exploit.
+
+// Encrypts a buffer of whole blocks in place, without padding.
+pub fn ecb_encrypt_blocks<C: BlockCipher>(cipher: &C, data: &mut [u8]) -> Result<(), CryptoError> {
+    let block_size = cipher.block_size();
+    block_cipher::check_whole_blocks(block_size, data)?;
+    for block in data.chunks_mut(block_size) {
+        cipher.encrypt_block(block)?;
+    }
+    Ok(())
+}
+
+// Decrypts a buffer of whole blocks in place, leaving any padding in place.
+pub fn ecb_decrypt_blocks<C: BlockCipher>(cipher: &C, data: &mut [u8]) -> Result<(), CryptoError> {
+    let block_size = cipher.block_size();
+    block_cipher::check_whole_blocks(block_size, data)?;
+    for block in data.chunks_mut(block_size) {
+        cipher.decrypt_block(block)?;
+    }
+    Ok(())
+}
+
+// PKCS#7 pads then encrypts a message of any length.
+pub fn ecb_encrypt<C: BlockCipher>(cipher: &C, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let mut data = padding::pkcs7_pad(plaintext, cipher.block_size())?;
+    ecb_encrypt_blocks(cipher, &mut data)?;
+    Ok(data)
+}
+
+// Decrypts then strips PKCS#7 padding, failing if the padding is malformed.
+pub fn ecb_decrypt<C: BlockCipher>(cipher: &C, ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let mut data = ciphertext.to_vec();
+    ecb_decrypt_blocks(cipher, &mut data)?;
+    let length = padding::pkcs7_unpad(&data, cipher.block_size())?.len();
+    data.truncate(length);
+    Ok(data)
+}
+
+// Repeated ciphertext blocks, which ECB produces from repeated plaintext blocks but which are
+// vanishingly unlikely otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EcbEvidence {
+    // Whole blocks examined; a trailing partial block is ignored
+    pub block_count: usize,
+    // Blocks equal to some earlier block
+    pub repeated_blocks: usize,
+    // Positions of each block value seen more than once, in order of first occurrence
+    pub repeats: Vec<Vec<usize>>,
+}
+
+impl EcbEvidence {
+    pub fn is_likely_ecb(&self) -> bool {
+        self.repeated_blocks > 0
+    }
+}
+
+// Counts repeated blocks of a ciphertext. A block size of 0 examines no blocks, so finds no
+// evidence.
+pub fn detect_ecb(ciphertext: &[u8], block_size: usize) -> EcbEvidence {
+    if block_size == 0 {
+        return EcbEvidence { block_count: 0, repeated_blocks: 0, repeats: Vec::new() };
+    }
+    let blocks: Vec<&[u8]> = ciphertext.chunks_exact(block_size).collect();
+    let occurrences = blocks.iter().cloned().occurrences();
+
+    let mut repeats: Vec<Vec<usize>> = occurrences.iter()
+        .filter(|&(_, &count)| count > 1)
+        .map(|(block, _)| {
+            (0..blocks.len()).filter(|&i| blocks[i] == *block).collect()
+        })
+        .collect();
+    repeats.sort_by_key(|positions| positions[0]);
+
+    EcbEvidence {
+        block_count: blocks.len(),
+        repeated_blocks: blocks.len() - occurrences.len(),
+        repeats,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EcbDetection {
+    // Index of the ciphertext among those searched, e.g. its line number less one
+    pub index: usize,
+    pub evidence: EcbEvidence,
+}
+
+// Ranks ciphertexts by how many repeated blocks they contain, most first, keeping the input
+// order among equals. Only those at the front with is_likely_ecb() are worth attacking.
+pub fn rank_ecb_candidates(ciphertexts: &[Vec<u8>], block_size: usize) -> Vec<EcbDetection> {
+    let mut detections: Vec<EcbDetection> = ciphertexts.iter()
+        .enumerate()
+        .map(|(index, ciphertext)| {
+            EcbDetection { index, evidence: detect_ecb(ciphertext, block_size) }
+        })
+        .collect();
+    detections.sort_by(|a, b| {
+        match b.evidence.repeated_blocks.cmp(&a.evidence.repeated_blocks) {
+            Ordering::Equal => a.index.cmp(&b.index),
+            ordering => ordering,
+        }
+    });
+    detections
+}
+
+#[cfg(test)]
+mod tests {
+    use aes::Aes;
+    use base64::Base64Decodable;
+    use cbc::cbc_encrypt;
+    use ecb::{ecb_encrypt, ecb_decrypt, ecb_encrypt_blocks, ecb_decrypt_blocks, detect_ecb,
+              rank_ecb_candidates};
+    use error::CryptoError;
+    use rand::{RngExt, SeedableRng};
+    use rand::rngs::StdRng;
+    use rustc_serialize::hex::FromHex;
+
+    #[test]
+    fn nist_sp_800_38a_ecb_aes128() {
+        let aes = Aes::new(&"2b7e151628aed2a6abf7158809cf4f3c".from_hex().unwrap()).unwrap();
+        let plaintext = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710"
+            .from_hex().unwrap();
+        let ciphertext = "3ad77bb40d7a3660a89ecaf32466ef97f5d3d58503b9699de785895a96fdbaaf\
+                          43b1cd7f598ece23881b00e3ed0306887b0c785e27e8ad3f8223207104725dd4"
+            .from_hex().unwrap();
+
+        let mut data = plaintext.clone();
+        ecb_encrypt_blocks(&aes, &mut data).unwrap();
+        assert_eq!(data, ciphertext);
+        ecb_decrypt_blocks(&aes, &mut data).unwrap();
+        assert_eq!(data, plaintext);
+    }
+
+    #[test]
+    fn challenge_7() {
+        // Opening blocks of the challenge data
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let mut data = b"CRIwqt4+szDbqkNY+I0qbDe3LQz0wiw0SuxBQtAM5TA=".to_vec()
+            .from_base64_to_u8_vec().unwrap();
+        ecb_decrypt_blocks(&aes, &mut data).unwrap();
+        assert_eq!(data, b"I'm back and I'm ringin' the bel");
+    }
+
+    #[test]
+    fn padded_round_trip() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        for length in 0..40 {
+            let plaintext: Vec<u8> = (0..length).map(|i| i as u8).collect();
+            let ciphertext = ecb_encrypt(&aes, &plaintext).unwrap();
+            assert_eq!(ciphertext.len(), (length / 16 + 1) * 16);
+            assert_eq!(ecb_decrypt(&aes, &ciphertext).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn equal_blocks_leak() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let ciphertext = ecb_encrypt(&aes, &[b'A'; 32]).unwrap();
+        assert_eq!(ciphertext[0..16], ciphertext[16..32]);
+    }
+
+    #[test]
+    fn invalid_input() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        assert_eq!(ecb_decrypt(&aes, &[0u8; 15]),
+                   Err(CryptoError::InvalidInputLength { block_size: 16, actual: 15 }));
+        assert_eq!(ecb_decrypt(&aes, &[]),
+                   Err(CryptoError::InvalidInputLength { block_size: 16, actual: 0 }));
+
+        // A block that decrypts to random bytes is almost never validly padded
+        let mut ciphertext = ecb_encrypt(&aes, b"some plaintext").unwrap();
+        ciphertext[15] ^= 1;
+        assert_eq!(ecb_decrypt(&aes, &ciphertext), Err(CryptoError::InvalidPadding));
+    }
+
+    #[test]
+    fn detect_ecb_evidence() {
+        // The ECB line of challenge 8
+        let ciphertext = "d880619740a8a19b7840a8a31c810a3d08649af70dc06f4fd5d2d69c744cd283\
+                          e2dd052f6b641dbf9d11b0348542bb5708649af70dc06f4fd5d2d69c744cd283\
+                          9475c9dfdbc1d46597949d9c7e82bf5a08649af70dc06f4fd5d2d69c744cd283\
+                          97a93eab8d6aecd566489154789a6b0308649af70dc06f4fd5d2d69c744cd283\
+                          d403180c98c8f6db1f2a3f9c4040deb0ab51b29933f2c123c58386b06fba186a"
+            .from_hex().unwrap();
+        let evidence = detect_ecb(&ciphertext, 16);
+        assert_eq!(evidence.block_count, 10);
+        assert_eq!(evidence.repeated_blocks, 3);
+        assert_eq!(evidence.repeats, vec![vec![1, 3, 5, 7]]);
+        assert!(evidence.is_likely_ecb());
+
+        let evidence = detect_ecb(b"AAAABBBBAAAACCCCBBBBAA", 4);
+        assert_eq!(evidence.block_count, 5);
+        assert_eq!(evidence.repeated_blocks, 2);
+        assert_eq!(evidence.repeats, vec![vec![0, 2], vec![1, 4]]);
+
+        assert!(!detect_ecb(b"abcdefgh", 4).is_likely_ecb());
+        assert_eq!(detect_ecb(b"AAAAAAAA", 0).block_count, 0);
+        assert!(!detect_ecb(b"AAAAAAAA", 0).is_likely_ecb());
+    }
+
+    #[test]
+    fn rank_ecb_lines() {
+        let mut rng = StdRng::seed_from_u64(35);
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let plaintext = b"Repeated blocks!Repeated blocks!Repeated blocks! and then the rest";
+        let mut ciphertexts: Vec<Vec<u8>> = (0..20)
+            .map(|_| {
+                let iv: Vec<u8> = (0..16).map(|_| rng.random()).collect();
+                cbc_encrypt(&aes, &iv, plaintext).unwrap()
+            })
+            .collect();
+        ciphertexts.insert(13, ecb_encrypt(&aes, plaintext).unwrap());
+
+        let ranked = rank_ecb_candidates(&ciphertexts, 16);
+        assert_eq!(ranked.len(), 21);
+        assert_eq!(ranked[0].index, 13);
+        assert_eq!(ranked[0].evidence.repeated_blocks, 2);
+        assert!(ranked[1..].iter().all(|d| !d.evidence.is_likely_ecb()));
+        assert_eq!(ranked[1].index, 0);
+    }
+}
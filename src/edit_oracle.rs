@@ -0,0 +1,124 @@
+use rand::Rng;
+use aes::{Aes, AES_BLOCK_SIZE};
+use ctr::{Ctr, CounterLayout};
+use error::CryptoError;
+use fixed_xor::FixedXor;
+use oracle;
+use stream_cipher::StreamCipher;
+
+// Challenge 25: random access read/write encryption, which lets a user rewrite part of a
+// ciphertext in place. With a seekable stream cipher the rewritten part is the new text XORed
+// with the same keystream as before, so editing in known text gives that keystream away.
+pub struct EditOracle<S: StreamCipher> {
+    cipher: S,
+}
+
+// The challenge's oracle: AES-CTR under a random key and nonce
+pub type CtrEditOracle = EditOracle<Ctr<Aes>>;
+
+impl<S: StreamCipher> EditOracle<S> {
+    pub fn new(cipher: S) -> EditOracle<S> {
+        EditOracle { cipher }
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.cipher.process(plaintext)
+    }
+
+    // Only for checking edits: an oracle that decrypted would leave nothing to attack
+    #[cfg(test)]
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.cipher.process(ciphertext)
+    }
+
+    // Replaces the plaintext at offset with newtext, which may run past the end of the message,
+    // and returns the new ciphertext.
+    pub fn edit(&self, ciphertext: &[u8], offset: usize, newtext: &[u8])
+                -> Result<Vec<u8>, CryptoError> {
+        if offset > ciphertext.len() {
+            return Err(CryptoError::OffsetOutOfRange { offset, length: ciphertext.len() });
+        }
+        let mut encrypted = newtext.to_vec();
+        self.cipher.apply_keystream(offset as u64, &mut encrypted)?;
+
+        let mut edited = ciphertext.to_vec();
+        let overlap = (ciphertext.len() - offset).min(encrypted.len());
+        edited[offset..offset + overlap].copy_from_slice(&encrypted[..overlap]);
+        edited.extend_from_slice(&encrypted[overlap..]);
+        Ok(edited)
+    }
+}
+
+impl CtrEditOracle {
+    pub fn with_random_key<R: Rng>(rng: &mut R) -> CtrEditOracle {
+        let key = oracle::random_bytes(rng, AES_BLOCK_SIZE);
+        let nonce = oracle::random_bytes(rng, 8);
+        let aes = Aes::new(&key).expect("AES accepts a 16 byte key");
+        let ctr = Ctr::new(aes, CounterLayout::cryptopals(), &nonce, 0)
+            .expect("Cryptopals layout fills an AES block");
+        EditOracle::new(ctr)
+    }
+}
+
+// Recovers the plaintext of a ciphertext by editing zeros over all of it, which yields the
+// keystream, and XORing that with the original.
+pub fn recover_by_editing<S: StreamCipher>(oracle: &EditOracle<S>, ciphertext: &[u8])
+                                           -> Result<Vec<u8>, String> {
+    let keystream = oracle.edit(ciphertext, 0, &vec![0; ciphertext.len()])?;
+    ciphertext.fixed_xor(&keystream[..])
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use edit_oracle::{EditOracle, CtrEditOracle, recover_by_editing};
+    use error::CryptoError;
+    use stream_cipher::StreamCipher;
+
+    static PLAINTEXT: &str = include_str!("english_corpus.txt");
+
+    #[test]
+    fn edit() {
+        let mut rng = StdRng::seed_from_u64(43);
+        let oracle = CtrEditOracle::with_random_key(&mut rng);
+        let ciphertext = oracle.encrypt(b"The quick brown fox").unwrap();
+
+        let edited = oracle.edit(&ciphertext, 4, b"quack").unwrap();
+        assert_eq!(oracle.decrypt(&edited).unwrap(), b"The quack brown fox");
+        assert_eq!(edited[..6], ciphertext[..6]);
+        assert_eq!(edited[7..], ciphertext[7..]);
+
+        let extended = oracle.edit(&ciphertext, 16, b"x and the lazy dog").unwrap();
+        assert_eq!(oracle.decrypt(&extended).unwrap(), b"The quick brown x and the lazy dog");
+        assert_eq!(oracle.edit(&ciphertext, 20, b"!"),
+                   Err(CryptoError::OffsetOutOfRange { offset: 20, length: 19 }));
+    }
+
+    #[test]
+    fn challenge_25() {
+        let mut rng = StdRng::seed_from_u64(43);
+        let oracle = CtrEditOracle::with_random_key(&mut rng);
+        let ciphertext = oracle.encrypt(PLAINTEXT.as_bytes()).unwrap();
+        assert_eq!(recover_by_editing(&oracle, &ciphertext).unwrap(), PLAINTEXT.as_bytes());
+    }
+
+    // A stream cipher that is not CTR, to show the attack does not depend on it
+    struct CountingStream;
+
+    impl StreamCipher for CountingStream {
+        fn apply_keystream(&self, offset: u64, data: &mut [u8]) -> Result<(), CryptoError> {
+            for (i, byte) in data.iter_mut().enumerate() {
+                *byte ^= (offset + i as u64).wrapping_mul(31).wrapping_add(7) as u8;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn any_stream_cipher() {
+        let oracle = EditOracle::new(CountingStream);
+        let ciphertext = oracle.encrypt(b"Not only counter mode").unwrap();
+        assert_eq!(recover_by_editing(&oracle, &ciphertext).unwrap(), b"Not only counter mode");
+    }
+}
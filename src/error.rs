@@ -0,0 +1,57 @@
+use std::error::Error;
+use std::fmt;
+
+// Errors from the cipher primitives, their modes of operation and padding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CryptoError {
+    // Key of a length the cipher does not support
+    InvalidKeyLength(usize),
+    // Block size a cipher, mode or padding scheme cannot work with
+    InvalidBlockSize(usize),
+    // Single block operation given the wrong number of bytes
+    InvalidBlockLength { expected: usize, actual: usize },
+    // Buffer that should be whole blocks but is not
+    InvalidInputLength { block_size: usize, actual: usize },
+    // IV or nonce of the wrong length for the cipher
+    InvalidIvLength { expected: usize, actual: usize },
+    // Buffers combined byte by byte that differ in length
+    LengthMismatch { left: usize, right: usize },
+    // Position past the end of the data it refers to
+    OffsetOutOfRange { offset: usize, length: usize },
+    // Padding that is malformed once decrypted
+    InvalidPadding,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CryptoError::InvalidKeyLength(length) => write!(f, "invalid key length {}", length),
+            CryptoError::InvalidBlockSize(size) => write!(f, "invalid block size {}", size),
+            CryptoError::InvalidBlockLength { expected, actual } => {
+                write!(f, "block must be {} bytes, got {}", expected, actual)
+            }
+            CryptoError::InvalidInputLength { block_size, actual } => {
+                write!(f, "input must be a multiple of {} bytes, got {}", block_size, actual)
+            }
+            CryptoError::InvalidIvLength { expected, actual } => {
+                write!(f, "IV must be {} bytes, got {}", expected, actual)
+            }
+            CryptoError::LengthMismatch { left, right } => {
+                write!(f, "input lengths differ: {} and {}", left, right)
+            }
+            CryptoError::OffsetOutOfRange { offset, length } => {
+                write!(f, "offset {} is past the end of {} bytes", offset, length)
+            }
+            CryptoError::InvalidPadding => write!(f, "invalid padding"),
+        }
+    }
+}
+
+impl Error for CryptoError {}
+
+// Lets functions that report errors as strings use ? on crypto results.
+impl From<CryptoError> for String {
+    fn from(error: CryptoError) -> String {
+        error.to_string()
+    }
+}
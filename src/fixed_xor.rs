@@ -1,4 +1,6 @@
-pub trait FixedXor<T> {
+use error::CryptoError;
+
+pub trait FixedXor<T: ?Sized> {
     fn fixed_xor(&self, other: & T) -> Result<Vec<u8>, String>;
 }
 
@@ -16,9 +18,32 @@ impl FixedXor<Vec<u8>> for Vec<u8> {
     }
 }
 
+impl FixedXor<[u8]> for [u8] {
+    fn fixed_xor(&self, other: &[u8]) -> Result<Vec<u8>, String> {
+        if self.len() != other.len() {
+            return Err("Input vectors do not have equal length".to_string());
+        }
+        Ok(self.iter().zip(other).map(|(a, b)| a ^ b).collect())
+    }
+}
+
+// XORs other into buffer, for callers such as the block cipher modes that chain in place.
+pub fn fixed_xor_in_place(buffer: &mut [u8], other: &[u8]) -> Result<(), CryptoError> {
+    if buffer.len() != other.len() {
+        return Err(CryptoError::LengthMismatch { left: buffer.len(), right: other.len() });
+    }
+
+    for (b, o) in buffer.iter_mut().zip(other) {
+        *b ^= o;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-    use fixed_xor::FixedXor;
+    use error::CryptoError;
+    use fixed_xor::{FixedXor, fixed_xor_in_place};
     use rustc_serialize::hex::FromHex;
 
     #[test]
@@ -29,5 +54,18 @@ mod tests {
 
         let result_of_or : Vec<u8> = hex_buffer1.fixed_xor(&hex_buffer2).unwrap();;
         assert_eq!(expected_output, result_of_or);
+
+        let mut buffer = hex_buffer1.clone();
+        fixed_xor_in_place(&mut buffer, &hex_buffer2).unwrap();
+        assert_eq!(expected_output, buffer);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_unequal_lengths() {
+        assert_eq!(vec![1u8, 2].fixed_xor(&vec![3u8]),
+                   Err("Input vectors do not have equal length".to_string()));
+        assert!([1u8, 2][..].fixed_xor(&[3u8][..]).is_err());
+        assert_eq!(fixed_xor_in_place(&mut [0u8; 3], &[0u8; 4]),
+                   Err(CryptoError::LengthMismatch { left: 3, right: 4 }));
+    }
+}
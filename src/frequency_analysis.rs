@@ -1,5 +1,8 @@
 use std::collections::btree_map::{BTreeMap, Entry};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
 use std::iter::IntoIterator;
+use std::path::Path;
 use std::str::Chars;
 
 pub trait FrequencyAnalysable {
@@ -75,6 +78,167 @@ pub fn character_frequency_distance(characters : Chars, character_frequencies :
 	return difference_from_specified_freqs;
 }
 
+// Approximate frequency of spaces in English text, which the letter frequency tables omit
+pub const ENGLISH_SPACE_FREQUENCY: f32 = 0.13;
+
+// Uppercase letters are much rarer than lowercase in prose, so only count for this much of
+// their letter's frequency
+const UPPERCASE_SCORE_WEIGHT: f32 = 0.5;
+
+// Scores characters by the mean expected frequency of each character, so text resembling the
+// given frequencies scores higher. Unlike character_frequency_distance this is meaningful for
+// short or non-contiguous samples such as the columns of a repeating XOR ciphertext.
+pub fn character_frequency_score(characters : Chars, character_frequencies : &BTreeMap<char, f32>) -> f32 {
+	let mut total_chars : f32 = 0.0;
+	let mut score : f32 = 0.0;
+	for c in characters {
+		total_chars += 1.0;
+		if c == ' ' {
+			score += ENGLISH_SPACE_FREQUENCY;
+		} else if let Some(lowercase) = c.to_lowercase().next() {
+			if let Some(frequency) = character_frequencies.get(&lowercase) {
+				if c.is_uppercase() {
+					score += *frequency * UPPERCASE_SCORE_WEIGHT;
+				} else {
+					score += *frequency;
+				}
+			}
+		}
+	}
+	if total_chars == 0.0 {
+		return 0.0;
+	}
+	score / total_chars
+}
+
+// Scores bytes decoded as a candidate plaintext with character_frequency_score, returning None
+// if they contain too many control characters to plausibly be text.
+pub fn plaintext_score(bytes : &[u8], character_frequencies : &BTreeMap<char, f32>) -> Option<f32> {
+	let text : String = bytes.iter().map(|&b| b as char).collect();
+	if control_character_frequency(text.chars()) >= 0.10 {
+		return None;
+	}
+	Some(character_frequency_score(text.chars(), character_frequencies))
+}
+
+// Returns the probability that two items drawn without replacement are equal. English text sits
+// around 0.066 over letters, uniformly random letters around 0.038.
+pub fn index_of_coincidence<FA : FrequencyAnalysable>(items : FA) -> f32 {
+	let occurrences = items.occurrences();
+	let total : usize = occurrences.values().sum();
+	if total < 2 {
+		return 0.0;
+	}
+	let coincidences : usize = occurrences.values().map(|&n| n * (n - 1)).sum();
+	(coincidences as f32) / ((total * (total - 1)) as f32)
+}
+
+// Returns the Shannon entropy in bits per item: 8 for uniformly random bytes, 0 for a constant.
+pub fn shannon_entropy<FA : FrequencyAnalysable>(items : FA) -> f64 {
+	let occurrences = items.occurrences();
+	let total : usize = occurrences.values().sum();
+	occurrences.values().map(|&n| {
+		let p = (n as f64) / (total as f64);
+		-p * p.log2()
+	}).sum()
+}
+
+// Log probabilities of letter n-grams, used to score candidate plaintexts of classical ciphers.
+// Only ASCII letters are considered and case is ignored.
+pub struct NgramModel {
+	n : usize,
+	log_probabilities : BTreeMap<Vec<u8>, f64>,
+	// Log probability given to n-grams never seen in training
+	floor : f64,
+}
+
+impl NgramModel {
+	// Builds a model from the n-grams of the letters of the given text.
+	pub fn from_corpus(corpus : &str, n : usize) -> Result<NgramModel, String> {
+		let letters = uppercase_letters(corpus.as_bytes());
+		if n == 0 || letters.len() < n {
+			return Err(format!("Cannot build {}-gram model from {} letters", n, letters.len()));
+		}
+		NgramModel::from_counts(n, letters.windows(n).map(|w| w.to_vec()).occurrences())
+	}
+
+	// Reads a model from lines of the form "NGRAM COUNT", the format most published n-gram
+	// tables are distributed in.
+	pub fn from_counts_file<P : AsRef<Path>>(path : P) -> Result<NgramModel, String> {
+		let file = match File::open(path.as_ref()) {
+			Ok(f) => f,
+			Err(e) => return Err(format!("Could not open n-gram file {}: {}", path.as_ref().display(), e))
+		};
+		let mut counts = BTreeMap::new();
+		let mut n = 0;
+		for line in BufReader::new(file).lines() {
+			let line = match line {
+				Ok(l) => l,
+				Err(e) => return Err(format!("Could not read n-gram file: {}", e))
+			};
+			let mut fields = line.split_whitespace();
+			let (ngram, count) = match (fields.next(), fields.next()) {
+				(Some(ngram), Some(count)) => (uppercase_letters(ngram.as_bytes()), count),
+				(None, _) => continue,
+				_ => return Err(format!("Malformed n-gram line: {}", line))
+			};
+			let count : usize = match count.parse() {
+				Ok(c) => c,
+				Err(_) => return Err(format!("Malformed n-gram count: {}", line))
+			};
+			if n == 0 {
+				n = ngram.len();
+			}
+			if ngram.len() != n || n == 0 {
+				return Err(format!("Inconsistent n-gram length in line: {}", line));
+			}
+			counts.insert(ngram, count);
+		}
+		NgramModel::from_counts(n, counts)
+	}
+
+	fn from_counts(n : usize, counts : BTreeMap<Vec<u8>, usize>) -> Result<NgramModel, String> {
+		let total : usize = counts.values().sum();
+		if total == 0 {
+			return Err("Cannot build n-gram model without any n-grams".to_owned());
+		}
+		let log_probabilities = counts.into_iter()
+			.map(|(ngram, count)| (ngram, ((count as f64) / (total as f64)).log10()))
+			.collect();
+		Ok(NgramModel {
+			n,
+			log_probabilities,
+			floor : (0.01 / (total as f64)).log10(),
+		})
+	}
+
+	// Trigram model trained on the bundled sample of English prose.
+	pub fn english_trigrams() -> NgramModel {
+		NgramModel::from_corpus(include_str!("english_corpus.txt"), 3)
+			.expect("Bundled English corpus is too short for a trigram model")
+	}
+
+	pub fn n(&self) -> usize {
+		self.n
+	}
+
+	// Sum of log probabilities of every n-gram in the letters of the text. Higher is better, and
+	// scores are only comparable between texts with the same number of letters.
+	pub fn score(&self, text : &[u8]) -> f64 {
+		let letters = uppercase_letters(text);
+		if letters.len() < self.n {
+			return 0.0;
+		}
+		letters.windows(self.n)
+			.map(|w| *self.log_probabilities.get(w).unwrap_or(&self.floor))
+			.sum()
+	}
+}
+
+fn uppercase_letters(bytes : &[u8]) -> Vec<u8> {
+	bytes.iter().filter(|b| b.is_ascii_alphabetic()).map(|b| b.to_ascii_uppercase()).collect()
+}
+
 pub fn alphabetic_uppercase_frequency(characters : Chars) -> f32 {
 	let mut total_alphabetic : f32 = 0.0;
 	let mut uppercase_alphabetic : f32 = 0.0;
@@ -177,4 +341,45 @@ mod tests {
 		let ratio = frequency_analysis::control_character_frequency(word.chars());
 		assert!(ratio > 0.374 && ratio < 0.376);
 	}
+
+	#[test]
+	fn character_frequency_score_test() {
+		let frequencies = frequency_analysis::english_letter_frequencies();
+		let english = frequency_analysis::character_frequency_score("the rain in spain".chars(), &frequencies);
+		let shouty = frequency_analysis::character_frequency_score("THE RAIN IN SPAIN".chars(), &frequencies);
+		let noise = frequency_analysis::character_frequency_score("zq#x~jv!".chars(), &frequencies);
+		assert!(english > shouty);
+		assert!(shouty > noise);
+		assert!(frequency_analysis::plaintext_score(b"\x00\x01\x02abc", &frequencies).is_none());
+	}
+
+	#[test]
+	fn index_of_coincidence_test() {
+		assert!(frequency_analysis::index_of_coincidence("aaaa".chars()) > 0.99);
+		assert!(frequency_analysis::index_of_coincidence("abcd".chars()) < 0.01);
+		// 2 * 1 / (4 * 3)
+		let ioc = frequency_analysis::index_of_coincidence(vec![1, 1, 2, 3]);
+		assert!(ioc > 0.166 && ioc < 0.167);
+	}
+
+	#[test]
+	fn shannon_entropy_test() {
+		assert_eq!(frequency_analysis::shannon_entropy(Vec::<u8>::new()), 0.0);
+		assert_eq!(frequency_analysis::shannon_entropy("aaaa".chars()), 0.0);
+		assert_eq!(frequency_analysis::shannon_entropy("abcd".chars()), 2.0);
+		let all_bytes : Vec<u8> = (0..=255).collect();
+		assert_eq!(frequency_analysis::shannon_entropy(all_bytes), 8.0);
+	}
+
+	#[test]
+	fn ngram_model_test() {
+		let model = frequency_analysis::NgramModel::english_trigrams();
+		assert_eq!(model.n(), 3);
+		assert!(model.score(b"the river was rising") > model.score(b"xqz jvkw pq fzzgtbmy"));
+		// Case and non-letters are ignored
+		assert_eq!(model.score(b"The River"), model.score(b"theriver"));
+
+		assert!(frequency_analysis::NgramModel::from_corpus("ab", 3).is_err());
+		assert!(frequency_analysis::NgramModel::from_counts_file("no_such_ngram_file.txt").is_err());
+	}
 }
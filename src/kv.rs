@@ -0,0 +1,109 @@
+// Structured cookies of the form key=value&key=value, as in challenge 13. The metacharacters
+// '&' and '=', and '%' itself, are percent encoded in keys and values, so nothing a user puts
+// in a value can add fields of its own.
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '%' => escaped.push_str("%25"),
+            '&' => escaped.push_str("%26"),
+            '=' => escaped.push_str("%3D"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let bytes = text.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            // from_str_radix alone would accept a sign, as in "%+1"
+            let byte = text.get(i + 1..i + 3)
+                .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("Invalid escape at offset {} of \"{}\"", i, text))?;
+            unescaped.push(byte);
+            i += 3;
+        } else {
+            unescaped.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(unescaped).map_err(|e| e.to_string())
+}
+
+// Encodes pairs in order, escaping metacharacters.
+pub fn encode_kv<K: AsRef<str>, V: AsRef<str>>(pairs: &[(K, V)]) -> String {
+    pairs.iter()
+        .map(|(key, value)| format!("{}={}", escape(key.as_ref()), escape(value.as_ref())))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+// Parses a cookie into its pairs, in order. Every field must have exactly one '='.
+pub fn parse_kv(cookie: &str) -> Result<Vec<(String, String)>, String> {
+    if cookie.is_empty() {
+        return Ok(Vec::new());
+    }
+    cookie.split('&')
+        .map(|field| {
+            let mut parts = field.split('=');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(key), Some(value), None) => Ok((unescape(key)?, unescape(value)?)),
+                _ => Err(format!("Malformed field \"{}\"", field)),
+            }
+        })
+        .collect()
+}
+
+// The value of the first field with the given key.
+pub fn kv_lookup<'a>(pairs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use kv::{encode_kv, parse_kv, kv_lookup};
+
+    #[test]
+    fn parse() {
+        let pairs = parse_kv("foo=bar&baz=qux&zap=zazzle").unwrap();
+        assert_eq!(pairs,
+                   vec![("foo".to_owned(), "bar".to_owned()),
+                        ("baz".to_owned(), "qux".to_owned()),
+                        ("zap".to_owned(), "zazzle".to_owned())]);
+        assert_eq!(kv_lookup(&pairs, "baz"), Some("qux"));
+        assert_eq!(kv_lookup(&pairs, "role"), None);
+        assert_eq!(parse_kv("").unwrap(), vec![]);
+        assert_eq!(parse_kv("empty=").unwrap(), vec![("empty".to_owned(), "".to_owned())]);
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(parse_kv("foo").is_err());
+        assert!(parse_kv("foo=bar=baz").is_err());
+        assert!(parse_kv("foo=bar&&baz=qux").is_err());
+        assert!(parse_kv("foo=%2").is_err());
+        assert!(parse_kv("foo=%zz").is_err());
+        assert!(parse_kv("foo=%+1").is_err());
+    }
+
+    #[test]
+    fn escaping() {
+        let cookie = encode_kv(&[("email", "foo@bar.com&role=admin"), ("uid", "10"), ("role", "user")]);
+        assert_eq!(cookie, "email=foo@bar.com%26role%3Dadmin&uid=10&role=user");
+        let pairs = parse_kv(&cookie).unwrap();
+        assert_eq!(pairs.len(), 3);
+        assert_eq!(kv_lookup(&pairs, "email"), Some("foo@bar.com&role=admin"));
+        assert_eq!(kv_lookup(&pairs, "role"), Some("user"));
+
+        let tricky = [("100%", "a=b&c"), ("", "%26")];
+        let parsed = parse_kv(&encode_kv(&tricky)).unwrap();
+        assert_eq!(parsed,
+                   vec![("100%".to_owned(), "a=b&c".to_owned()), ("".to_owned(), "%26".to_owned())]);
+    }
+}
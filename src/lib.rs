@@ -1,10 +1,41 @@
 extern crate rustc_serialize;
 extern crate num;
 extern crate rust_hamming_distance;
+extern crate rand;
 
+// Lints allowed on modules below are in code that predates the clippy gate, which is left as
+// written
+
+pub mod aes;
+#[allow(unused_parens, clippy::needless_return, clippy::manual_is_multiple_of)]
 pub mod base64;
+pub mod bit_flipping;
+pub mod block_cipher;
+pub mod byte_at_a_time;
+pub mod cbc;
+pub mod classical;
+pub mod ctr;
+pub mod cut_and_paste;
+pub mod ecb;
+pub mod edit_oracle;
+pub mod error;
+#[allow(redundant_semicolons, clippy::needless_lifetimes, clippy::needless_return)]
 pub mod fixed_xor;
+#[allow(clippy::needless_return, clippy::clone_on_copy)]
 pub mod frequency_analysis;
+pub mod kv;
+pub mod oracle;
+pub mod oracle_profile;
+pub mod padding;
+pub mod padding_oracle;
+pub mod prng;
+pub mod randomness;
+#[allow(clippy::needless_return, clippy::len_zero)]
 pub mod single_byte_xor;
+pub mod stream_cipher;
+#[allow(clippy::needless_return, clippy::vec_init_then_push)]
 pub mod repeating_xor;
+pub mod results;
+pub mod rolling_xor;
+#[allow(clippy::needless_return, clippy::ptr_arg, clippy::unnecessary_to_owned)]
 pub mod utility;
@@ -1,27 +1,384 @@
+extern crate matasano_crypto_challenges;
 extern crate rustc_serialize;
-extern crate num;
-extern crate rust_hamming_distance;
 
-pub mod base64;
-pub mod fixed_xor;
-pub mod frequency_analysis;
-pub mod single_byte_xor;
-pub mod repeating_xor;
-pub mod utility;
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+use rustc_serialize::hex::{FromHex, ToHex};
+use rustc_serialize::json::ToJson;
+use matasano_crypto_challenges::base64::{Base64Encodable, Base64Decodable};
+use matasano_crypto_challenges::fixed_xor::FixedXor;
+use matasano_crypto_challenges::frequency_analysis::{self, FrequencyAnalysable};
+use matasano_crypto_challenges::repeating_xor::{self, KeyCharset, KeyConstraints};
+use matasano_crypto_challenges::results::{self, SingleByteXorResult, RepeatingXorResult,
+                                          DetectionResult};
+use matasano_crypto_challenges::single_byte_xor;
 
-use repeating_xor::{RepeatingXorEncodable, RepeatingXorDecodable};
-use std::borrow::Borrow;
+static USAGE: &str = "Usage: matasano_crypto_challenges <command> [options]
+
+Commands:
+    encode base64|hex [--decode]    Encode the input, or decode it with --decode
+    xor fixed (--key K | --key-hex H | --key-file F)
+                                    XOR the input with a key of the same length
+    xor repeat (--key K | --key-hex H | --key-file F)
+                                    XOR the input with a repeating key
+    crack single-xor                Recover a single byte XOR key and plaintext
+    crack repeat-xor [--min-key-length N] [--max-key-length N]
+                     [--charset any|printable|alphanumeric|hex] [--dictionary F]
+                                    Recover a repeating XOR key and plaintext
+    detect                          Find the input line encrypted with single byte XOR
+    analyze freq                    Print byte frequencies and index of coincidence
+
+Options:
+    --input F                       Read from F instead of stdin
+    --output F                      Write to F instead of stdout
+    --input-format raw|hex|base64   How the input is encoded (default raw, detect defaults to hex)
+    --output-format raw|hex|base64  How to encode output bytes (default raw); reports are text
+    --json                          Write crack and detect results as JSON instead
+
+Keys and scores recovered by crack and detect are reported on stderr, unless --json is given.
+";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Raw,
+    Hex,
+    Base64,
+}
+
+impl Format {
+    fn parse(name: &str) -> Result<Format, String> {
+        match name {
+            "raw" => Ok(Format::Raw),
+            "hex" => Ok(Format::Hex),
+            "base64" => Ok(Format::Base64),
+            _ => Err(format!("Unknown format: {}", name)),
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, String> {
+        match *self {
+            Format::Raw => Ok(bytes.to_vec()),
+            Format::Hex => {
+                let text: String = bytes.iter()
+                    .map(|&b| b as char)
+                    .filter(|c| !c.is_whitespace())
+                    .collect();
+                text.from_hex().map_err(|e| format!("Invalid hex input: {}", e))
+            }
+            Format::Base64 => {
+                let chars: Vec<char> = bytes.iter()
+                    .map(|&b| b as char)
+                    .filter(|c| !c.is_whitespace())
+                    .collect();
+                chars.from_base64_to_u8_vec()
+            }
+        }
+    }
+
+    fn encode(&self, bytes: &[u8]) -> Vec<u8> {
+        match *self {
+            Format::Raw => bytes.to_vec(),
+            Format::Hex => (bytes.to_hex() + "\n").into_bytes(),
+            Format::Base64 => {
+                let mut encoded: String = bytes.to_vec().to_base64_char_vec().into_iter().collect();
+                encoded.push('\n');
+                encoded.into_bytes()
+            }
+        }
+    }
+}
+
+struct Options {
+    positional: Vec<String>,
+    input: Option<String>,
+    output: Option<String>,
+    input_format: Option<Format>,
+    output_format: Format,
+    key: Option<Vec<u8>>,
+    decode: bool,
+    json: bool,
+    constraints: KeyConstraints,
+}
+
+fn parse_count(value: &str, name: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("{} must be a number, got: {}", name, value))
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    match File::open(path) {
+        Ok(mut f) => {
+            f.read_to_end(&mut bytes).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        }
+        Err(e) => return Err(format!("Could not open {}: {}", path, e)),
+    }
+    Ok(bytes)
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        positional: Vec::new(),
+        input: None,
+        output: None,
+        input_format: None,
+        output_format: Format::Raw,
+        key: None,
+        decode: false,
+        json: false,
+        constraints: KeyConstraints::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            options.positional.push(arg.clone());
+            continue;
+        }
+        if arg == "--decode" {
+            options.decode = true;
+            continue;
+        }
+        if arg == "--json" {
+            options.json = true;
+            continue;
+        }
+        let value = match args.next() {
+            Some(v) => v,
+            None => return Err(format!("Missing value for {}", arg)),
+        };
+        match arg.as_str() {
+            "--input" => options.input = Some(value.clone()),
+            "--output" => options.output = Some(value.clone()),
+            "--input-format" => options.input_format = Some(Format::parse(value)?),
+            "--output-format" => options.output_format = Format::parse(value)?,
+            "--key" => options.key = Some(value.as_bytes().to_vec()),
+            "--key-hex" => options.key = Some(Format::Hex.decode(value.as_bytes())?),
+            "--key-file" => options.key = Some(read_file(value)?),
+            "--min-key-length" => {
+                options.constraints.min_key_length = parse_count(value, "--min-key-length")?
+            }
+            "--max-key-length" => {
+                options.constraints.max_key_length = parse_count(value, "--max-key-length")?
+            }
+            "--charset" => {
+                options.constraints.charset = match value.as_str() {
+                    "any" => KeyCharset::Any,
+                    "printable" => KeyCharset::PrintableAscii,
+                    "alphanumeric" => KeyCharset::Alphanumeric,
+                    "hex" => KeyCharset::Hex,
+                    _ => return Err(format!("Unknown charset: {}", value)),
+                }
+            }
+            "--dictionary" => {
+                options.constraints.dictionary = repeating_xor::load_key_dictionary(value)?
+            }
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+fn read_input(options: &Options) -> Result<Vec<u8>, String> {
+    match options.input {
+        Some(ref path) => read_file(path),
+        None => {
+            let mut bytes = Vec::new();
+            io::stdin()
+                .read_to_end(&mut bytes)
+                .map_err(|e| format!("Could not read stdin: {}", e))?;
+            Ok(bytes)
+        }
+    }
+}
+
+fn write_raw_output(options: &Options, bytes: &[u8]) -> Result<(), String> {
+    let result = match options.output {
+        Some(ref path) => File::create(path).and_then(|mut f| f.write_all(bytes)),
+        None => io::stdout().write_all(bytes),
+    };
+    result.map_err(|e| format!("Could not write output: {}", e))
+}
+
+fn write_output(options: &Options, bytes: &[u8]) -> Result<(), String> {
+    write_raw_output(options, &options.output_format.encode(bytes))
+}
+
+fn write_json<T: ToJson>(options: &Options, result: &T) -> Result<(), String> {
+    write_raw_output(options, (results::to_pretty_json(result) + "\n").as_bytes())
+}
+
+// Number of candidates included in JSON output, best first
+const JSON_CANDIDATES: usize = 5;
+
+fn require_key(options: &Options) -> Result<&[u8], String> {
+    match options.key {
+        Some(ref key) if !key.is_empty() => Ok(key),
+        _ => Err("A non-empty --key, --key-hex or --key-file is required".to_owned()),
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_options(args)?;
+    let positional: Vec<&str> = options.positional.iter().map(|s| s.as_str()).collect();
+    let input_format = options.input_format.unwrap_or(Format::Raw);
+    let frequencies = frequency_analysis::english_letter_frequencies();
+
+    match positional.as_slice() {
+        ["encode", encoding] => {
+            let format = Format::parse(encoding)?;
+            let input = input_format.decode(&read_input(&options)?)?;
+            if options.decode {
+                let decoded = format.decode(&input)?;
+                write_output(&options, &decoded)
+            } else {
+                let encoded = format.encode(&input);
+                write_output(&options, &encoded)
+            }
+        }
+        ["xor", "fixed"] => {
+            let key = require_key(&options)?.to_vec();
+            let input = input_format.decode(&read_input(&options)?)?;
+            write_output(&options, &input.fixed_xor(&key)?)
+        }
+        ["xor", "repeat"] => {
+            let key = require_key(&options)?;
+            let input = input_format.decode(&read_input(&options)?)?;
+            write_output(&options, &repeating_xor::apply_repeating_key(&input, key))
+        }
+        ["crack", "single-xor"] => {
+            let input = input_format.decode(&read_input(&options)?)?;
+            let candidates = single_byte_xor::find_single_byte_xor_candidates(&input, &frequencies);
+            if options.json {
+                let results: Vec<SingleByteXorResult> = candidates.iter()
+                    .take(JSON_CANDIDATES)
+                    .map(SingleByteXorResult::from)
+                    .collect();
+                return write_json(&options, &results);
+            }
+            match candidates.first() {
+                Some(best) => {
+                    eprintln!("key: {:02x} score: {}", best.key, best.score);
+                    write_output(&options, &best.plaintext)
+                }
+                None => Err("No single byte XOR key produces plausible text".to_owned()),
+            }
+        }
+        ["crack", "repeat-xor"] => {
+            let input = input_format.decode(&read_input(&options)?)?;
+            let candidates =
+                repeating_xor::find_repeating_xor_keys(&input, &options.constraints, &frequencies)?;
+            if options.json {
+                let key_sizes =
+                    repeating_xor::find_repeating_xor_key_lengths(&input, &options.constraints)?;
+                let result = RepeatingXorResult::new(&input, key_sizes, &candidates);
+                return write_json(&options, &result);
+            }
+            match candidates.first() {
+                Some(best) => {
+                    eprintln!("key: {} ({:?}) score: {}",
+                              best.key.to_hex(),
+                              String::from_utf8_lossy(&best.key),
+                              best.score);
+                    write_output(&options, &repeating_xor::apply_repeating_key(&input, &best.key))
+                }
+                None => Err("No repeating XOR key satisfying the constraints was found".to_owned()),
+            }
+        }
+        ["detect"] => {
+            // Each line is a separate ciphertext, so decode them individually
+            let line_format = options.input_format.unwrap_or(Format::Hex);
+            let input = read_input(&options)?;
+            let mut ciphertexts = Vec::new();
+            let lines = input.split(|&b| b == b'\n')
+                .filter(|l| !l.iter().all(|b| b.is_ascii_whitespace()));
+            for line in lines {
+                ciphertexts.push(line_format.decode(line)?);
+            }
+            let detections = single_byte_xor::detect_single_byte_xor(&ciphertexts, &frequencies);
+            if options.json {
+                let results: Vec<DetectionResult> = detections.iter()
+                    .take(JSON_CANDIDATES)
+                    .map(|d| DetectionResult::new(&ciphertexts, d))
+                    .collect();
+                return write_json(&options, &results);
+            }
+            match detections.first() {
+                Some(best) => {
+                    eprintln!("line: {} key: {:02x} score: {}",
+                              best.index + 1,
+                              best.candidate.key,
+                              best.candidate.score);
+                    write_output(&options, &best.candidate.plaintext)
+                }
+                None => Err("No line decodes to plausible text".to_owned()),
+            }
+        }
+        ["analyze", "freq"] => {
+            let input = input_format.decode(&read_input(&options)?)?;
+            let mut occurrences: Vec<(u8, usize)> =
+                input.iter().cloned().occurrences().into_iter().collect();
+            occurrences.sort_by(|&(b1, n1), &(b2, n2)| n2.cmp(&n1).then(b1.cmp(&b2)));
+            let mut report = format!("bytes: {}\nindex of coincidence: {:.5}\n",
+                                     input.len(),
+                                     frequency_analysis::index_of_coincidence(input.iter()));
+            for (byte, count) in occurrences {
+                let shown = if byte.is_ascii_graphic() { byte as char } else { '.' };
+                report += &format!("{:02x} {} {} {:.5}\n",
+                                   byte,
+                                   shown,
+                                   count,
+                                   count as f32 / input.len() as f32);
+            }
+            // A text report, so the output format does not apply
+            write_raw_output(&options, report.as_bytes())
+        }
+        _ => Err(USAGE.to_owned()),
+    }
+}
 
 fn main() {
-    //let hex_bytes = "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736".from_hex().unwrap();
-    //repeating_xor::find_textual_decode_candidates(hex_bytes.borrow(), &frequency_analysis::english_letter_frequencies());
-
-    let plaintext_string = "Burning 'em, if you ain't quick and nimble\nI go crazy when I hear a cymbal";
-    let key = "ICE";
-    let byte_vec = plaintext_string.as_bytes().iter().repeating_xor_encode(key.as_bytes().iter()).unwrap();
-    let byte_slice : &[u8] = byte_vec.borrow();
-    let decoded_string : String = byte_slice.find_repeating_xor_decode(&frequency_analysis::english_letter_frequencies()).unwrap();
-    println!("{:?}", decoded_string);
-
-    
-}
\ No newline at end of file
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        let _ = writeln!(io::stderr(), "{}", e.trim_end());
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Format, parse_options};
+    use matasano_crypto_challenges::repeating_xor::KeyCharset;
+
+    #[test]
+    fn format_round_trips() {
+        let bytes = b"Cooking MC's like a pound of bacon";
+        for format in [Format::Raw, Format::Hex, Format::Base64].iter() {
+            assert_eq!(format.decode(&format.encode(bytes)).unwrap(), bytes.to_vec());
+        }
+        assert_eq!(Format::Hex.encode(&[0x49, 0x43]), b"4943\n".to_vec());
+        assert!(Format::Hex.decode(b"4g").is_err());
+        assert!(Format::parse("octal").is_err());
+    }
+
+    #[test]
+    fn options() {
+        let args: Vec<String> = ["crack", "repeat-xor", "--charset", "hex", "--max-key-length", "8",
+                                 "--input-format", "base64", "--json"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let options = parse_options(&args).unwrap();
+        assert_eq!(options.positional, vec!["crack".to_owned(), "repeat-xor".to_owned()]);
+        assert_eq!(options.constraints.max_key_length, 8);
+        assert!(options.input_format == Some(Format::Base64));
+        assert!(options.json);
+        match options.constraints.charset {
+            KeyCharset::Hex => {}
+            _ => panic!("Expected hex charset"),
+        }
+
+        assert!(parse_options(&["--key".to_owned()]).is_err());
+        assert!(parse_options(&["--bogus".to_owned(), "1".to_owned()]).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use rand::{Rng, RngExt};
+use aes::{Aes, AES_BLOCK_SIZE};
+use cbc;
+use ecb;
+
+// Chosen plaintext oracles: black boxes that encrypt whatever we give them, usually wrapped in
+// data of their own. The attacks only see them through the Oracle trait, so a stand-in for a
+// remote service can be swapped in for the stock oracles below.
+pub trait Oracle {
+    fn query(&mut self, input: &[u8]) -> Vec<u8>;
+}
+
+impl<F: FnMut(&[u8]) -> Vec<u8>> Oracle for F {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        self(input)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockMode {
+    Ecb,
+    Cbc,
+}
+
+pub fn random_bytes<R: Rng>(rng: &mut R, length: usize) -> Vec<u8> {
+    (0..length).map(|_| rng.random()).collect()
+}
+
+fn random_aes<R: Rng>(rng: &mut R) -> Aes {
+    Aes::new(&random_bytes(rng, AES_BLOCK_SIZE)).expect("AES accepts a 16 byte key")
+}
+
+// Challenge 11: every query uses a fresh random key, wraps the input in 5 to 10 random bytes on
+// each side, and encrypts under ECB or, with a random IV, CBC, chosen by a coin flip.
+pub struct RandomModeOracle<R: Rng> {
+    rng: R,
+    last_mode: Option<BlockMode>,
+}
+
+impl<R: Rng> RandomModeOracle<R> {
+    pub fn new(rng: R) -> RandomModeOracle<R> {
+        RandomModeOracle { rng, last_mode: None }
+    }
+
+    // The mode used for the most recent query, to check guesses against
+    pub fn last_mode(&self) -> Option<BlockMode> {
+        self.last_mode
+    }
+}
+
+impl<R: Rng> Oracle for RandomModeOracle<R> {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        let aes = random_aes(&mut self.rng);
+        let prefix_length = self.rng.random_range(5..11);
+        let suffix_length = self.rng.random_range(5..11);
+        let mut plaintext = random_bytes(&mut self.rng, prefix_length);
+        plaintext.extend_from_slice(input);
+        plaintext.extend(random_bytes(&mut self.rng, suffix_length));
+
+        if self.rng.random() {
+            self.last_mode = Some(BlockMode::Ecb);
+            ecb::ecb_encrypt(&aes, &plaintext)
+        } else {
+            self.last_mode = Some(BlockMode::Cbc);
+            let iv = random_bytes(&mut self.rng, AES_BLOCK_SIZE);
+            cbc::cbc_encrypt(&aes, &iv, &plaintext)
+        }.expect("padded input is whole blocks")
+    }
+}
+
+// Challenges 12 and 14: AES-ECB under a fixed random key of prefix || input || secret suffix.
+pub struct EcbOracle {
+    aes: Aes,
+    prefix: Vec<u8>,
+    suffix: Vec<u8>,
+}
+
+impl EcbOracle {
+    // Challenge 12, with no prefix
+    pub fn with_suffix<R: Rng>(suffix: &[u8], rng: &mut R) -> EcbOracle {
+        EcbOracle { aes: random_aes(rng), prefix: Vec::new(), suffix: suffix.to_vec() }
+    }
+
+    // Challenge 14, with a fixed prefix of up to 63 random bytes
+    pub fn with_random_prefix<R: Rng>(suffix: &[u8], rng: &mut R) -> EcbOracle {
+        let prefix_length = rng.random_range(0..64);
+        EcbOracle {
+            aes: random_aes(rng),
+            prefix: random_bytes(rng, prefix_length),
+            suffix: suffix.to_vec(),
+        }
+    }
+
+    pub fn prefix_length(&self) -> usize {
+        self.prefix.len()
+    }
+
+    pub fn suffix_length(&self) -> usize {
+        self.suffix.len()
+    }
+}
+
+impl Oracle for EcbOracle {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut plaintext = self.prefix.clone();
+        plaintext.extend_from_slice(input);
+        plaintext.extend_from_slice(&self.suffix);
+        ecb::ecb_encrypt(&self.aes, &plaintext).expect("padded input is whole blocks")
+    }
+}
+
+// Wraps an oracle to count the queries an attack makes. Given a budget, queries past it are
+// refused with an empty ciphertext, as a rate limited service might.
+pub struct CountingOracle<O: Oracle> {
+    inner: O,
+    queries: usize,
+    budget: Option<usize>,
+}
+
+impl<O: Oracle> CountingOracle<O> {
+    pub fn new(inner: O) -> CountingOracle<O> {
+        CountingOracle { inner, queries: 0, budget: None }
+    }
+
+    pub fn with_budget(inner: O, budget: usize) -> CountingOracle<O> {
+        CountingOracle { inner, queries: 0, budget: Some(budget) }
+    }
+
+    // Queries made, including any refused
+    pub fn queries(&self) -> usize {
+        self.queries
+    }
+
+    pub fn remaining(&self) -> Option<usize> {
+        self.budget.map(|budget| budget.saturating_sub(self.queries))
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining() == Some(0)
+    }
+
+    pub fn inner(&self) -> &O {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> O {
+        self.inner
+    }
+}
+
+impl<O: Oracle> Oracle for CountingOracle<O> {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        let refused = self.is_exhausted();
+        self.queries += 1;
+        if refused {
+            return Vec::new();
+        }
+        self.inner.query(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use ecb;
+    use oracle::{Oracle, BlockMode, RandomModeOracle, EcbOracle, CountingOracle};
+
+    #[test]
+    fn random_mode_oracle() {
+        let mut oracle = RandomModeOracle::new(StdRng::seed_from_u64(36));
+        assert_eq!(oracle.last_mode(), None);
+        let mut modes = Vec::new();
+        for _ in 0..20 {
+            let ciphertext = oracle.query(&[b'A'; 48]);
+            // 48 bytes plus 10 to 20 of padding either side, then PKCS#7
+            assert!(ciphertext.len() == 64 || ciphertext.len() == 80);
+            let mode = oracle.last_mode().unwrap();
+            let evidence = ecb::detect_ecb(&ciphertext, 16);
+            assert_eq!(evidence.is_likely_ecb(), mode == BlockMode::Ecb);
+            modes.push(mode);
+        }
+        assert!(modes.contains(&BlockMode::Ecb) && modes.contains(&BlockMode::Cbc));
+    }
+
+    #[test]
+    fn ecb_oracle() {
+        let mut rng = StdRng::seed_from_u64(36);
+        let mut oracle = EcbOracle::with_suffix(b"secret", &mut rng);
+        assert_eq!(oracle.prefix_length(), 0);
+        assert_eq!(oracle.query(b"").len(), 16);
+        assert_eq!(oracle.query(&[0; 10]).len(), 32);
+        // Deterministic under a fixed key
+        assert_eq!(oracle.query(b"abc"), oracle.query(b"abc"));
+
+        let mut oracle = EcbOracle::with_random_prefix(b"secret", &mut rng);
+        let length = oracle.prefix_length() + oracle.suffix_length();
+        assert_eq!(oracle.query(b"").len(), (length / 16 + 1) * 16);
+    }
+
+    #[test]
+    fn counting_oracle() {
+        let mut oracle = CountingOracle::with_budget(|input: &[u8]| input.to_vec(), 2);
+        assert_eq!(oracle.remaining(), Some(2));
+        assert_eq!(oracle.query(b"one"), b"one");
+        assert_eq!(oracle.query(b"two"), b"two");
+        assert!(oracle.is_exhausted());
+        assert_eq!(oracle.query(b"three"), b"");
+        assert_eq!(oracle.queries(), 3);
+
+        let mut unlimited = CountingOracle::new(|input: &[u8]| input.to_vec());
+        for _ in 0..10 {
+            unlimited.query(b"x");
+        }
+        assert_eq!(unlimited.queries(), 10);
+        assert_eq!(unlimited.remaining(), None);
+    }
+}
@@ -0,0 +1,176 @@
+use ecb;
+use oracle::{Oracle, BlockMode};
+
+// Largest block size looked for, in bytes
+const MAX_BLOCK_SIZE: usize = 256;
+
+// What a chosen plaintext oracle does with our input, learned only from its ciphertexts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OracleProfile {
+    pub block_size: usize,
+    pub mode: BlockMode,
+    // Fixed bytes the oracle puts before our input
+    pub prefix_length: usize,
+    // Fixed bytes the oracle puts after our input
+    pub suffix_length: usize,
+}
+
+// Finds the block size of an oracle that pads as PKCS#7 does, by growing the input until the
+// ciphertext grows. Returns the block size and the input length at which it grew.
+fn find_block_size<O: Oracle>(oracle: &mut O) -> Result<(usize, usize), String> {
+    let initial_length = oracle.query(&[]).len();
+    if initial_length == 0 {
+        return Err("Oracle returned no ciphertext".to_owned());
+    }
+    for input_length in 1..MAX_BLOCK_SIZE + 1 {
+        let length = oracle.query(&vec![0; input_length]).len();
+        if length > initial_length {
+            return Ok((length - initial_length, input_length));
+        }
+        if length < initial_length {
+            return Err("Oracle ciphertext shrank as its input grew".to_owned());
+        }
+    }
+    Err(format!("Ciphertext did not grow with up to {} bytes of input", MAX_BLOCK_SIZE))
+}
+
+// Decides between ECB and CBC from one query. Three blocks of equal bytes always hold two
+// aligned equal blocks, whatever comes before them, and only ECB encrypts them alike. This
+// also works against oracles that pick a fresh key, padding and mode for every query.
+pub fn detect_mode<O: Oracle>(oracle: &mut O, block_size: usize) -> BlockMode {
+    let ciphertext = oracle.query(&vec![0; 3 * block_size]);
+    if ecb::detect_ecb(&ciphertext, block_size).is_likely_ecb() {
+        BlockMode::Ecb
+    } else {
+        BlockMode::Cbc
+    }
+}
+
+fn first_differing_block(a: &[u8], b: &[u8], block_size: usize) -> Option<usize> {
+    a.chunks(block_size).zip(b.chunks(block_size)).position(|(x, y)| x != y)
+}
+
+// Finds the prefix length from which ciphertext blocks a change to the input first affects.
+// In both ECB and CBC a block depends only on the plaintext up to its end, so once the changed
+// byte is pushed past the block holding the end of the prefix, that block stops changing.
+fn find_prefix_length<O: Oracle>(oracle: &mut O, block_size: usize) -> Result<usize, String> {
+    let prefix_block = first_differing_block(&oracle.query(&[0]), &oracle.query(&[1]), block_size)
+        .ok_or("Oracle ciphertext does not depend on its input")?;
+
+    for pad in 1..block_size + 1 {
+        let mut input = vec![0; pad + 1];
+        let a = oracle.query(&input);
+        input[pad] = 1;
+        let b = oracle.query(&input);
+        if first_differing_block(&a, &b, block_size) != Some(prefix_block) {
+            return Ok((prefix_block + 1) * block_size - pad);
+        }
+    }
+    Err("Could not align input to a block boundary".to_owned())
+}
+
+// Profiles a deterministic oracle: the same input must always give the same ciphertext.
+pub fn profile_oracle<O: Oracle>(oracle: &mut O) -> Result<OracleProfile, String> {
+    let (block_size, growth_length) = find_block_size(oracle)?;
+    let mode = detect_mode(oracle, block_size);
+    let prefix_length = find_prefix_length(oracle, block_size)?;
+
+    // The ciphertext grows when the fixed bytes and input exactly fill the blocks
+    let fixed_length = oracle.query(&[]).len().checked_sub(growth_length)
+        .ok_or("Oracle ciphertext shrank between queries")?;
+    if prefix_length > fixed_length {
+        return Err("Oracle prefix and suffix lengths are inconsistent".to_owned());
+    }
+
+    Ok(OracleProfile {
+        block_size,
+        mode,
+        prefix_length,
+        suffix_length: fixed_length - prefix_length,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use aes::Aes;
+    use cbc;
+    use padding;
+    use oracle::{BlockMode, RandomModeOracle, EcbOracle};
+    use oracle_profile::{OracleProfile, profile_oracle, detect_mode};
+
+    #[test]
+    fn detect_random_mode() {
+        // Challenge 11
+        let mut oracle = RandomModeOracle::new(StdRng::seed_from_u64(37));
+        for _ in 0..30 {
+            let mode = detect_mode(&mut oracle, 16);
+            assert_eq!(Some(mode), oracle.last_mode());
+        }
+    }
+
+    #[test]
+    fn profile_ecb_oracles() {
+        let mut rng = StdRng::seed_from_u64(37);
+        for suffix_length in 0..34 {
+            let mut oracle = EcbOracle::with_suffix(&vec![b'x'; suffix_length], &mut rng);
+            assert_eq!(profile_oracle(&mut oracle),
+                       Ok(OracleProfile {
+                           block_size: 16,
+                           mode: BlockMode::Ecb,
+                           prefix_length: 0,
+                           suffix_length,
+                       }));
+        }
+        for _ in 0..20 {
+            let mut oracle = EcbOracle::with_random_prefix(b"the secret suffix", &mut rng);
+            let profile = profile_oracle(&mut oracle).unwrap();
+            assert_eq!(profile.mode, BlockMode::Ecb);
+            assert_eq!(profile.prefix_length, oracle.prefix_length());
+            assert_eq!(profile.suffix_length, 17);
+        }
+    }
+
+    #[test]
+    fn profile_cbc_oracle() {
+        // Fixed key and IV, as in challenge 16
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let mut oracle = |input: &[u8]| {
+            let mut plaintext = b"comment1=cooking%20MCs;userdata=".to_vec();
+            plaintext.extend_from_slice(input);
+            plaintext.extend_from_slice(b";comment2=%20like%20a%20pound%20of%20bacon");
+            cbc::cbc_encrypt(&aes, &[0; 16], &plaintext).unwrap()
+        };
+        assert_eq!(profile_oracle(&mut oracle),
+                   Ok(OracleProfile {
+                       block_size: 16,
+                       mode: BlockMode::Cbc,
+                       prefix_length: 32,
+                       suffix_length: 42,
+                   }));
+    }
+
+    #[test]
+    fn profile_failures() {
+        let mut silent = |_: &[u8]| Vec::new();
+        assert!(profile_oracle(&mut silent).is_err());
+        let mut constant = |_: &[u8]| vec![0; 16];
+        assert!(profile_oracle(&mut constant).is_err());
+        let mut stream = |input: &[u8]| input.iter().map(|b| b ^ 0x5a).collect::<Vec<u8>>();
+        assert!(profile_oracle(&mut stream).is_err());
+
+        // Padded but unencrypted, until it stops answering empty input
+        let mut empty_queries = 0;
+        let mut flaky = |input: &[u8]| {
+            if input.is_empty() {
+                empty_queries += 1;
+                if empty_queries > 1 {
+                    return Vec::new();
+                }
+            }
+            padding::pkcs7_pad(input, 16).unwrap()
+        };
+        assert!(profile_oracle(&mut flaky).is_err());
+    }
+}
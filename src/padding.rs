@@ -0,0 +1,204 @@
+use rand::{Rng, RngExt};
+use error::CryptoError;
+
+// Padding schemes that fill out the last block of a message for a block cipher mode. All but
+// zero padding add between 1 and block_size bytes and end with the number added, so they can
+// be removed unambiguously and only work with block sizes up to 255.
+
+fn check_block_size(block_size: usize) -> Result<(), CryptoError> {
+    if block_size == 0 || block_size > 255 {
+        return Err(CryptoError::InvalidBlockSize(block_size));
+    }
+    Ok(())
+}
+
+fn pad_length(data: &[u8], block_size: usize) -> Result<usize, CryptoError> {
+    check_block_size(block_size)?;
+    Ok(block_size - data.len() % block_size)
+}
+
+// Checks a padded message is whole blocks and reads its padding length from the last byte.
+fn trailing_pad_length(data: &[u8], block_size: usize) -> Result<usize, CryptoError> {
+    check_block_size(block_size)?;
+    if data.is_empty() || !data.len().is_multiple_of(block_size) {
+        return Err(CryptoError::InvalidPadding);
+    }
+    let pad_length = data[data.len() - 1] as usize;
+    if pad_length == 0 || pad_length > block_size {
+        return Err(CryptoError::InvalidPadding);
+    }
+    Ok(pad_length)
+}
+
+// Checks the bytes of the last block before its final byte that lie inside the padding. Every
+// byte of the block is examined and mismatches accumulated, rather than returning at the first
+// bad byte, so the time taken does not depend on where the padding goes wrong.
+fn check_pad_bytes(data: &[u8], block_size: usize, pad_length: usize, expected: u8)
+                   -> Result<(), CryptoError> {
+    let last_block = &data[data.len() - block_size..data.len() - 1];
+    let first_pad_index = block_size - pad_length;
+    let mut mismatches = 0u8;
+    for (i, &byte) in last_block.iter().enumerate() {
+        let in_padding = (i >= first_pad_index) as u8;
+        mismatches |= in_padding & (byte != expected) as u8;
+    }
+    if mismatches != 0 {
+        return Err(CryptoError::InvalidPadding);
+    }
+    Ok(())
+}
+
+// PKCS#7 appends n bytes of value n, adding a whole block when the data already fills one.
+pub fn pkcs7_pad(data: &[u8], block_size: usize) -> Result<Vec<u8>, CryptoError> {
+    let pad_length = pad_length(data, block_size)?;
+    let mut padded = data.to_vec();
+    padded.resize(data.len() + pad_length, pad_length as u8);
+    Ok(padded)
+}
+
+pub fn pkcs7_unpad(data: &[u8], block_size: usize) -> Result<&[u8], CryptoError> {
+    let pad_length = trailing_pad_length(data, block_size)?;
+    check_pad_bytes(data, block_size, pad_length, pad_length as u8)?;
+    Ok(&data[..data.len() - pad_length])
+}
+
+// ANSI X.923 appends n - 1 zero bytes then the byte n.
+pub fn ansi_x923_pad(data: &[u8], block_size: usize) -> Result<Vec<u8>, CryptoError> {
+    let pad_length = pad_length(data, block_size)?;
+    let mut padded = data.to_vec();
+    padded.resize(data.len() + pad_length - 1, 0);
+    padded.push(pad_length as u8);
+    Ok(padded)
+}
+
+pub fn ansi_x923_unpad(data: &[u8], block_size: usize) -> Result<&[u8], CryptoError> {
+    let pad_length = trailing_pad_length(data, block_size)?;
+    check_pad_bytes(data, block_size, pad_length, 0)?;
+    Ok(&data[..data.len() - pad_length])
+}
+
+// ISO 10126 appends n - 1 random bytes then the byte n. Only the final byte can be checked.
+pub fn iso_10126_pad<R: Rng>(data: &[u8], block_size: usize, rng: &mut R)
+                             -> Result<Vec<u8>, CryptoError> {
+    let pad_length = pad_length(data, block_size)?;
+    let mut padded = data.to_vec();
+    padded.extend((1..pad_length).map(|_| rng.random::<u8>()));
+    padded.push(pad_length as u8);
+    Ok(padded)
+}
+
+pub fn iso_10126_unpad(data: &[u8], block_size: usize) -> Result<&[u8], CryptoError> {
+    let pad_length = trailing_pad_length(data, block_size)?;
+    Ok(&data[..data.len() - pad_length])
+}
+
+// Zero padding appends as few zero bytes as fill the last block, possibly none. It cannot be
+// told apart from zero bytes at the end of the message, which unpadding also strips from the
+// last block, so it only suits data known not to end in zeros.
+pub fn zero_pad(data: &[u8], block_size: usize) -> Result<Vec<u8>, CryptoError> {
+    let pad_length = pad_length(data, block_size)? % block_size;
+    let mut padded = data.to_vec();
+    padded.resize(data.len() + pad_length, 0);
+    Ok(padded)
+}
+
+pub fn zero_unpad(data: &[u8], block_size: usize) -> Result<&[u8], CryptoError> {
+    check_block_size(block_size)?;
+    if !data.len().is_multiple_of(block_size) {
+        return Err(CryptoError::InvalidPadding);
+    }
+    // Padding never fills a whole block, so at most block_size - 1 zeros are stripped
+    let zeros = data.iter().rev().take_while(|&&b| b == 0).take(block_size - 1).count();
+    Ok(&data[..data.len() - zeros])
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use error::CryptoError;
+    use padding::{pkcs7_pad, pkcs7_unpad, ansi_x923_pad, ansi_x923_unpad, iso_10126_pad,
+                  iso_10126_unpad, zero_pad, zero_unpad};
+
+    #[test]
+    fn pkcs7() {
+        // Challenges 9 and 15
+        assert_eq!(pkcs7_pad(b"YELLOW SUBMARINE", 20).unwrap(), b"YELLOW SUBMARINE\x04\x04\x04\x04");
+        assert_eq!(pkcs7_pad(b"", 4).unwrap(), b"\x04\x04\x04\x04");
+        assert_eq!(pkcs7_pad(b"abc", 1).unwrap(), b"abc\x01");
+        assert_eq!(pkcs7_unpad(b"ICE ICE BABY\x04\x04\x04\x04", 16).unwrap(), b"ICE ICE BABY");
+        assert_eq!(pkcs7_unpad(b"ICE ICE BABY\x05\x05\x05\x05", 16), Err(CryptoError::InvalidPadding));
+        assert_eq!(pkcs7_unpad(b"ICE ICE BABY\x01\x02\x03\x04", 16), Err(CryptoError::InvalidPadding));
+    }
+
+    #[test]
+    fn pkcs7_round_trips() {
+        for &block_size in &[1, 8, 16, 255] {
+            for length in 0..300 {
+                let data: Vec<u8> = (0..length).map(|i| i as u8).collect();
+                let padded = pkcs7_pad(&data, block_size).unwrap();
+                assert!(padded.len().is_multiple_of(block_size) && padded.len() > data.len());
+                assert_eq!(pkcs7_unpad(&padded, block_size).unwrap(), &data[..]);
+            }
+        }
+    }
+
+    #[test]
+    fn pkcs7_rejects() {
+        // Every single byte change to the padding of a full padding block is caught
+        let padded = pkcs7_pad(b"", 16).unwrap();
+        for i in 0..16 {
+            for bit in 0..8 {
+                let mut corrupted = padded.clone();
+                corrupted[i] ^= 1 << bit;
+                assert_eq!(pkcs7_unpad(&corrupted, 16), Err(CryptoError::InvalidPadding));
+            }
+        }
+        // Only the last byte matters when it claims a short padding
+        assert_eq!(pkcs7_unpad(b"\x02\x02\x02\x01", 4).unwrap(), b"\x02\x02\x02");
+        assert_eq!(pkcs7_unpad(b"\x03\x02\x02", 3).unwrap(), b"\x03");
+        assert_eq!(pkcs7_unpad(b"abc\x00", 4), Err(CryptoError::InvalidPadding));
+        assert_eq!(pkcs7_unpad(b"abc\x05", 4), Err(CryptoError::InvalidPadding));
+        assert_eq!(pkcs7_unpad(b"abc\x01\x01", 4), Err(CryptoError::InvalidPadding));
+        assert_eq!(pkcs7_unpad(b"", 4), Err(CryptoError::InvalidPadding));
+        assert_eq!(pkcs7_pad(b"", 256), Err(CryptoError::InvalidBlockSize(256)));
+        assert_eq!(pkcs7_unpad(b"\x01", 0), Err(CryptoError::InvalidBlockSize(0)));
+    }
+
+    #[test]
+    fn ansi_x923() {
+        assert_eq!(ansi_x923_pad(b"abcde", 8).unwrap(), b"abcde\x00\x00\x03");
+        assert_eq!(ansi_x923_pad(b"abcd", 4).unwrap(), b"abcd\x00\x00\x00\x04");
+        assert_eq!(ansi_x923_unpad(b"abcde\x00\x00\x03", 8).unwrap(), b"abcde");
+        assert_eq!(ansi_x923_unpad(b"abcde\x00\x01\x03", 8), Err(CryptoError::InvalidPadding));
+        assert_eq!(ansi_x923_unpad(b"abcde\x03\x03\x03", 8), Err(CryptoError::InvalidPadding));
+    }
+
+    #[test]
+    fn iso_10126() {
+        let mut rng = StdRng::seed_from_u64(34);
+        for length in 0..20 {
+            let data = vec![b'x'; length];
+            let padded = iso_10126_pad(&data, 8, &mut rng).unwrap();
+            assert_eq!(padded.len(), (length / 8 + 1) * 8);
+            assert_eq!(iso_10126_unpad(&padded, 8).unwrap(), &data[..]);
+        }
+        assert_eq!(iso_10126_unpad(b"abcde\x91\x3c\x03", 8).unwrap(), b"abcde");
+        assert_eq!(iso_10126_unpad(b"abcde\x91\x3c\x09", 8), Err(CryptoError::InvalidPadding));
+    }
+
+    #[test]
+    fn zero() {
+        assert_eq!(zero_pad(b"abcde", 8).unwrap(), b"abcde\x00\x00\x00");
+        assert_eq!(zero_pad(b"abcdefgh", 8).unwrap(), b"abcdefgh");
+        assert_eq!(zero_pad(b"", 8).unwrap(), b"");
+        assert_eq!(zero_unpad(b"abcde\x00\x00\x00", 8).unwrap(), b"abcde");
+        // Trailing zeros of the message are lost
+        assert_eq!(zero_unpad(&zero_pad(b"ab\x00", 4).unwrap(), 4).unwrap(), b"ab");
+        assert_eq!(zero_unpad(b"abcde", 8), Err(CryptoError::InvalidPadding));
+        // A whole block of zeros is message, not padding
+        assert_eq!(zero_unpad(b"abcd\x00\x00\x00\x00", 4).unwrap(), b"abcd\x00");
+        assert_eq!(zero_unpad(&[0; 8], 4).unwrap(), &[0; 5]);
+        assert_eq!(zero_unpad(b"", 4).unwrap(), b"");
+    }
+}
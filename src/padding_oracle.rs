@@ -0,0 +1,287 @@
+use rand::Rng;
+use aes::{Aes, AES_BLOCK_SIZE};
+use cbc;
+use oracle;
+use padding;
+
+// The CBC padding oracle attack. An oracle that says only whether a ciphertext decrypts to
+// valid PKCS#7 padding reveals the block cipher's output for any block: we send the block
+// after a forged IV and adjust the IV byte by byte, from the end, until the padding is valid.
+// XORing that decrypted block with the real previous block, or the real IV, gives the
+// plaintext. Run backwards, the same trick forges a ciphertext for any plaintext.
+
+pub trait PaddingOracle {
+    fn is_valid(&mut self, iv: &[u8], ciphertext: &[u8]) -> bool;
+}
+
+impl<F: FnMut(&[u8], &[u8]) -> bool> PaddingOracle for F {
+    fn is_valid(&mut self, iv: &[u8], ciphertext: &[u8]) -> bool {
+        self(iv, ciphertext)
+    }
+}
+
+// Challenge 17: a server holding a random AES key that encrypts under CBC with a random IV,
+// and answers whether ciphertexts it is sent are validly padded.
+pub struct LocalPaddingOracle {
+    aes: Aes,
+}
+
+impl LocalPaddingOracle {
+    pub fn new<R: Rng>(rng: &mut R) -> LocalPaddingOracle {
+        let key = oracle::random_bytes(rng, AES_BLOCK_SIZE);
+        LocalPaddingOracle { aes: Aes::new(&key).expect("AES accepts a 16 byte key") }
+    }
+
+    // Returns the IV and the ciphertext
+    pub fn encrypt<R: Rng>(&self, plaintext: &[u8], rng: &mut R) -> (Vec<u8>, Vec<u8>) {
+        let iv = oracle::random_bytes(rng, AES_BLOCK_SIZE);
+        let ciphertext = cbc::cbc_encrypt(&self.aes, &iv, plaintext)
+            .expect("padded input is whole blocks");
+        (iv, ciphertext)
+    }
+
+    pub fn decrypt(&self, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(cbc::cbc_decrypt(&self.aes, iv, ciphertext)?)
+    }
+}
+
+impl PaddingOracle for LocalPaddingOracle {
+    fn is_valid(&mut self, iv: &[u8], ciphertext: &[u8]) -> bool {
+        self.decrypt(iv, ciphertext).is_ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PaddingOracleStats {
+    pub queries: usize,
+    // Blocks put through the block cipher by way of the oracle
+    pub blocks: usize,
+    // Guesses the oracle accepted that turned out to pad as \x02\x02 or longer, not \x01
+    pub false_positives: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaddingOracleDecryption {
+    pub plaintext: Vec<u8>,
+    pub stats: PaddingOracleStats,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaddingOracleEncryption {
+    pub iv: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    pub stats: PaddingOracleStats,
+}
+
+fn query<P: PaddingOracle>(oracle: &mut P,
+                           stats: &mut PaddingOracleStats,
+                           iv: &[u8],
+                           block: &[u8])
+                           -> bool {
+    stats.queries += 1;
+    oracle.is_valid(iv, block)
+}
+
+// Finds the block cipher's decryption of a single block, before it is XORed with the IV.
+fn decrypt_block<P: PaddingOracle>(oracle: &mut P,
+                                   block: &[u8],
+                                   stats: &mut PaddingOracleStats)
+                                   -> Result<Vec<u8>, String> {
+    let block_size = block.len();
+    let mut decrypted = vec![0u8; block_size];
+    let mut forged_iv = vec![0u8; block_size];
+
+    for pad in 1..block_size + 1 {
+        let position = block_size - pad;
+        // Make the bytes already found decrypt to the pad value
+        for i in position + 1..block_size {
+            forged_iv[i] = decrypted[i] ^ pad as u8;
+        }
+
+        let mut found = None;
+        for guess in 0..256 {
+            forged_iv[position] = guess as u8;
+            if !query(oracle, stats, &forged_iv, block) {
+                continue;
+            }
+            // The last byte may have padded validly as \x02\x02 or longer instead of \x01. Then
+            // changing the byte before it breaks the padding, but for a true \x01 it cannot.
+            if pad == 1 && position > 0 {
+                forged_iv[position - 1] ^= 1;
+                let still_valid = query(oracle, stats, &forged_iv, block);
+                forged_iv[position - 1] ^= 1;
+                if !still_valid {
+                    stats.false_positives += 1;
+                    continue;
+                }
+            }
+            found = Some(guess as u8);
+            break;
+        }
+
+        match found {
+            Some(guess) => decrypted[position] = guess ^ pad as u8,
+            None => return Err(format!("No byte gave valid padding at block offset {}", position)),
+        }
+    }
+    stats.blocks += 1;
+    Ok(decrypted)
+}
+
+fn check_lengths(block_size: usize, iv: &[u8], ciphertext: &[u8]) -> Result<(), String> {
+    if block_size == 0 || iv.len() != block_size {
+        return Err(format!("IV must be one {} byte block", block_size));
+    }
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(block_size) {
+        return Err(format!("Ciphertext must be whole {} byte blocks", block_size));
+    }
+    Ok(())
+}
+
+// Decrypts every block of a CBC ciphertext, the first by way of its IV, then strips the padding.
+pub fn padding_oracle_decrypt<P: PaddingOracle>(oracle: &mut P,
+                                                block_size: usize,
+                                                iv: &[u8],
+                                                ciphertext: &[u8])
+                                                -> Result<PaddingOracleDecryption, String> {
+    check_lengths(block_size, iv, ciphertext)?;
+    let mut stats = PaddingOracleStats::default();
+    let mut padded = Vec::with_capacity(ciphertext.len());
+    let mut previous = iv;
+    for block in ciphertext.chunks(block_size) {
+        let decrypted = decrypt_block(oracle, block, &mut stats)?;
+        padded.extend(decrypted.iter().zip(previous).map(|(d, p)| d ^ p));
+        previous = block;
+    }
+    let plaintext = padding::pkcs7_unpad(&padded, block_size)?.to_vec();
+    Ok(PaddingOracleDecryption { plaintext, stats })
+}
+
+// Forges an IV and ciphertext that decrypt to plaintext, without the key. Works from the last
+// block back: each block's decryption is found through the oracle, and the block before it is
+// chosen to turn that into the plaintext wanted.
+pub fn padding_oracle_encrypt<P: PaddingOracle>(oracle: &mut P,
+                                                block_size: usize,
+                                                plaintext: &[u8])
+                                                -> Result<PaddingOracleEncryption, String> {
+    let padded = padding::pkcs7_pad(plaintext, block_size)?;
+    let mut stats = PaddingOracleStats::default();
+
+    // Blocks, built from the end, of which the first will be the IV
+    let mut blocks = vec![vec![0u8; block_size]];
+    for plaintext_block in padded.chunks(block_size).rev() {
+        let decrypted = decrypt_block(oracle, &blocks[blocks.len() - 1], &mut stats)?;
+        blocks.push(decrypted.iter().zip(plaintext_block).map(|(d, p)| d ^ p).collect());
+    }
+    blocks.reverse();
+
+    let iv = blocks[0].clone();
+    let ciphertext = blocks[1..].concat();
+    Ok(PaddingOracleEncryption { iv, ciphertext, stats })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use base64::Base64Decodable;
+    use block_cipher::BlockCipher;
+    use cbc;
+    use error::CryptoError;
+    use fixed_xor::fixed_xor_in_place;
+    use padding_oracle::{PaddingOracle, LocalPaddingOracle, padding_oracle_decrypt,
+                         padding_oracle_encrypt};
+
+    static CHALLENGE_17_STRINGS: [&str; 10] = [
+        "MDAwMDAwTm93IHRoYXQgdGhlIHBhcnR5IGlzIGp1bXBpbmc=",
+        "MDAwMDAxV2l0aCB0aGUgYmFzcyBraWNrZWQgaW4gYW5kIHRoZSBWZWdhJ3MgYXJlIHB1bXBpbic=",
+        "MDAwMDAyUXVpY2sgdG8gdGhlIHBvaW50LCB0byB0aGUgcG9pbnQsIG5vIGZha2luZw==",
+        "MDAwMDAzQ29va2luZyBNQydzIGxpa2UgYSBwb3VuZCBvZiBiYWNvbg==",
+        "MDAwMDA0QnVybmluZyAnZW0sIGlmIHlvdSBhaW4ndCBxdWljayBhbmQgbmltYmxl",
+        "MDAwMDA1SSBnbyBjcmF6eSB3aGVuIEkgaGVhciBhIGN5bWJhbA==",
+        "MDAwMDA2QW5kIGEgaGlnaCBoYXQgd2l0aCBhIHNvdXBlZCB1cCB0ZW1wbw==",
+        "MDAwMDA3SSdtIG9uIGEgcm9sbCwgaXQncyB0aW1lIHRvIGdvIHNvbG8=",
+        "MDAwMDA4b2xsaW4nIGluIG15IGZpdmUgcG9pbnQgb2g=",
+        "MDAwMDA5aXRoIG15IHJhZy10b3AgZG93biBzbyBteSBoYWlyIGNhbiBibG93",
+    ];
+
+    #[test]
+    fn challenge_17() {
+        let mut rng = StdRng::seed_from_u64(41);
+        let mut oracle = LocalPaddingOracle::new(&mut rng);
+        for encoded in CHALLENGE_17_STRINGS.iter() {
+            let plaintext = encoded.as_bytes().to_vec().from_base64_to_u8_vec().unwrap();
+            let (iv, ciphertext) = oracle.encrypt(&plaintext, &mut rng);
+            let decryption = padding_oracle_decrypt(&mut oracle, 16, &iv, &ciphertext).unwrap();
+            assert_eq!(decryption.plaintext, plaintext);
+            assert_eq!(decryption.stats.blocks, ciphertext.len() / 16);
+            // At most 256 guesses per byte, plus a check of the accepted last byte guess per block
+            // and one more for each false positive that check rejected
+            let stats = decryption.stats;
+            assert!(stats.false_positives <= stats.blocks);
+            assert!(stats.queries <= stats.blocks * (16 * 256 + 1) + stats.false_positives);
+        }
+    }
+
+    #[test]
+    fn forge_ciphertext() {
+        let mut rng = StdRng::seed_from_u64(41);
+        let mut oracle = LocalPaddingOracle::new(&mut rng);
+        let plaintext = b"Forged without the key, block by block";
+        let encryption = padding_oracle_encrypt(&mut oracle, 16, plaintext).unwrap();
+        assert_eq!(encryption.ciphertext.len(), 48);
+        assert_eq!(encryption.stats.blocks, 3);
+        assert_eq!(oracle.decrypt(&encryption.iv, &encryption.ciphertext).unwrap(), &plaintext[..]);
+    }
+
+    // Toy cipher that XORs blocks with its key, so tests can choose what a block decrypts to
+    struct XorCipher {
+        key: Vec<u8>,
+    }
+
+    impl BlockCipher for XorCipher {
+        fn block_size(&self) -> usize {
+            self.key.len()
+        }
+
+        fn encrypt_block(&self, block: &mut [u8]) -> Result<(), CryptoError> {
+            fixed_xor_in_place(block, &self.key)
+        }
+
+        fn decrypt_block(&self, block: &mut [u8]) -> Result<(), CryptoError> {
+            fixed_xor_in_place(block, &self.key)
+        }
+    }
+
+    #[test]
+    fn false_positive() {
+        // The zero block decrypts to the key, so with a zero forged IV its second to last byte
+        // is \x02. Guess 1 then pads as \x02\x02 before guess 2 gives the true \x01.
+        let mut key = vec![0u8; 16];
+        key[14] = 0x02;
+        key[15] = 0x03;
+        let cipher = XorCipher { key };
+        let mut oracle = |iv: &[u8], ciphertext: &[u8]| {
+            cbc::cbc_decrypt(&cipher, iv, ciphertext).is_ok()
+        };
+
+        let plaintext = b"hello, world";
+        // Under a zero IV the one ciphertext block is the padded plaintext XOR the key, which
+        // as an IV makes the zero block decrypt to the plaintext
+        let iv = cbc::cbc_encrypt(&cipher, &[0; 16], plaintext).unwrap();
+        let ciphertext = vec![0u8; 16];
+        assert!(oracle.is_valid(&iv, &ciphertext));
+
+        let decryption = padding_oracle_decrypt(&mut oracle, 16, &iv, &ciphertext).unwrap();
+        assert_eq!(decryption.plaintext, &plaintext[..]);
+        assert_eq!(decryption.stats.false_positives, 1);
+    }
+
+    #[test]
+    fn invalid_lengths() {
+        let mut oracle = |_: &[u8], _: &[u8]| true;
+        assert!(padding_oracle_decrypt(&mut oracle, 16, &[0; 8], &[0; 16]).is_err());
+        assert!(padding_oracle_decrypt(&mut oracle, 16, &[0; 16], &[0; 20]).is_err());
+        assert!(padding_oracle_decrypt(&mut oracle, 16, &[0; 16], &[]).is_err());
+    }
+}
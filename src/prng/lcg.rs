@@ -0,0 +1,323 @@
+use std::ops::Range;
+use prng::SeedablePrng;
+
+// Linear congruential generators with power of two moduli: state = multiplier * state +
+// increment mod 2^modulus_bits, with each output a slice of the new state's bits. Bits of the
+// state never affect bits below them, which is what makes truncated outputs easy to crack.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LcgParameters {
+    pub multiplier: u64,
+    pub increment: u64,
+    pub modulus_bits: u32,
+    // Outputs are the output_bits bits of the state starting at output_shift
+    pub output_shift: u32,
+    pub output_bits: u32,
+}
+
+// glibc's random() after initstate with an 8 byte state (TYPE_0). The default rand() is an
+// additive feedback generator instead, which is not an LCG.
+pub const GLIBC_TYPE_0: LcgParameters = LcgParameters {
+    multiplier: 1_103_515_245,
+    increment: 12_345,
+    modulus_bits: 31,
+    output_shift: 0,
+    output_bits: 31,
+};
+
+// java.util.Random, whose nextInt() is the top 32 bits of a 48 bit state
+pub const JAVA_RANDOM: LcgParameters = LcgParameters {
+    multiplier: 0x5_deec_e66d,
+    increment: 0xb,
+    modulus_bits: 48,
+    output_shift: 16,
+    output_bits: 32,
+};
+
+// The Microsoft C runtime's rand(), which returns bits 16 to 30 of a 32 bit state
+pub const MSVC_RAND: LcgParameters = LcgParameters {
+    multiplier: 214_013,
+    increment: 2_531_011,
+    modulus_bits: 32,
+    output_shift: 16,
+    output_bits: 15,
+};
+
+fn mask(bits: u32) -> u64 {
+    if bits >= 64 { !0 } else { (1 << bits) - 1 }
+}
+
+impl LcgParameters {
+    pub fn state_mask(&self) -> u64 {
+        mask(self.modulus_bits)
+    }
+
+    pub fn step(&self, state: u64) -> u64 {
+        self.multiplier.wrapping_mul(state).wrapping_add(self.increment) & self.state_mask()
+    }
+
+    pub fn output(&self, state: u64) -> u64 {
+        (state >> self.output_shift) & mask(self.output_bits)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lcg {
+    parameters: LcgParameters,
+    state: u64,
+}
+
+impl Lcg {
+    pub fn new(parameters: LcgParameters, state: u64) -> Lcg {
+        Lcg { parameters, state: state & parameters.state_mask() }
+    }
+
+    // srandom(seed) for TYPE_0, which replaces a zero seed with one
+    pub fn glibc(seed: u32) -> Lcg {
+        Lcg::new(GLIBC_TYPE_0, if seed == 0 { 1 } else { seed as u64 })
+    }
+
+    // new Random(seed), which scrambles the seed with the multiplier
+    pub fn java(seed: u64) -> Lcg {
+        Lcg::new(JAVA_RANDOM, seed ^ JAVA_RANDOM.multiplier)
+    }
+
+    // srand(seed)
+    pub fn msvc(seed: u32) -> Lcg {
+        Lcg::new(MSVC_RAND, seed as u64)
+    }
+
+    pub fn parameters(&self) -> LcgParameters {
+        self.parameters
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_output(&mut self) -> u64 {
+        self.state = self.parameters.step(self.state);
+        self.parameters.output(self.state)
+    }
+
+    pub fn take(&mut self, count: usize) -> Vec<u64> {
+        (0..count).map(|_| self.next_output()).collect()
+    }
+}
+
+// Seedable forms of each family, for seed searches.
+pub struct GlibcRandom(pub Lcg);
+pub struct JavaRandom(pub Lcg);
+pub struct MsvcRand(pub Lcg);
+
+impl SeedablePrng for GlibcRandom {
+    fn from_seed(seed: u64) -> GlibcRandom {
+        GlibcRandom(Lcg::glibc(seed as u32))
+    }
+
+    fn next_output(&mut self) -> u64 {
+        self.0.next_output()
+    }
+}
+
+impl SeedablePrng for JavaRandom {
+    fn from_seed(seed: u64) -> JavaRandom {
+        JavaRandom(Lcg::java(seed))
+    }
+
+    fn next_output(&mut self) -> u64 {
+        self.0.next_output()
+    }
+}
+
+impl SeedablePrng for MsvcRand {
+    fn from_seed(seed: u64) -> MsvcRand {
+        MsvcRand(Lcg::msvc(seed as u32))
+    }
+
+    fn next_output(&mut self) -> u64 {
+        self.0.next_output()
+    }
+}
+
+// The most hidden low bits worth brute forcing
+const MAX_HIDDEN_BITS: u32 = 24;
+
+// A generator recovered from its outputs. The state is the one after the last output seen,
+// with any bits above the outputs set to zero since they never affect an output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LcgRecovery {
+    pub parameters: LcgParameters,
+    pub state: u64,
+}
+
+impl LcgRecovery {
+    pub fn generator(&self) -> Lcg {
+        Lcg::new(self.parameters, self.state)
+    }
+
+    // The outputs that follow those the generator was recovered from
+    pub fn predict(&self, count: usize) -> Vec<u64> {
+        self.generator().take(count)
+    }
+}
+
+// Follows outputs from the state that produced the first, returning the final state.
+fn follow(parameters: &LcgParameters, mut state: u64, outputs: &[u64]) -> Option<u64> {
+    for &output in &outputs[1..] {
+        state = parameters.step(state);
+        if parameters.output(state) != output {
+            return None;
+        }
+    }
+    Some(state)
+}
+
+// States consistent with outputs, stopping after two as one more means they are ambiguous.
+fn candidate_states(parameters: &LcgParameters, outputs: &[u64]) -> Vec<u64> {
+    let high = outputs[0] << parameters.output_shift;
+    (0..1u64 << parameters.output_shift)
+        .filter_map(|low| follow(parameters, high | low, outputs))
+        .take(2)
+        .collect()
+}
+
+fn check_outputs(parameters: &LcgParameters, outputs: &[u64]) -> Result<(), String> {
+    if outputs.len() < 2 {
+        return Err(format!("need at least 2 outputs, got {}", outputs.len()));
+    }
+    if parameters.output_shift > MAX_HIDDEN_BITS {
+        return Err(format!("{} hidden state bits are too many to brute force", parameters.output_shift));
+    }
+    match outputs.iter().find(|&&output| output > mask(parameters.output_bits)) {
+        Some(output) => Err(format!("output {} is wider than {} bits", output, parameters.output_bits)),
+        None => Ok(()),
+    }
+}
+
+// Recovers the state of a generator with known parameters by trying every value of the state
+// bits below its outputs against the later outputs.
+pub fn recover_state(parameters: LcgParameters, outputs: &[u64]) -> Result<LcgRecovery, String> {
+    check_outputs(&parameters, outputs)?;
+    let candidates = candidate_states(&parameters, outputs);
+    match candidates.len() {
+        0 => Err("no state produces these outputs".to_owned()),
+        1 => Ok(LcgRecovery { parameters, state: candidates[0] }),
+        _ => Err("several states produce these outputs, more are needed".to_owned()),
+    }
+}
+
+// Recovers the increment as well as the state when only the multiplier is known. Outputs of the
+// whole state give the increment directly; otherwise each increment in increments is tried, and
+// it is an error for more than one to fit.
+pub fn recover_increment(multiplier: u64,
+                         shape: LcgParameters,
+                         outputs: &[u64],
+                         increments: Range<u64>)
+                         -> Result<LcgRecovery, String> {
+    let mut parameters = LcgParameters { multiplier, ..shape };
+    check_outputs(&parameters, outputs)?;
+
+    if parameters.output_shift == 0 && parameters.output_bits >= parameters.modulus_bits {
+        let increment = outputs[1].wrapping_sub(multiplier.wrapping_mul(outputs[0])) & parameters.state_mask();
+        if !increments.contains(&increment) {
+            return Err(format!("increment {} is outside the range searched", increment));
+        }
+        parameters.increment = increment;
+        return recover_state(parameters, outputs);
+    }
+
+    // Every increment is tried, as a later one fitting too means the outputs are ambiguous
+    let mut found = None;
+    for increment in increments {
+        parameters.increment = increment;
+        for state in candidate_states(&parameters, outputs) {
+            if found.is_some() {
+                return Err("several states produce these outputs, more are needed".to_owned());
+            }
+            found = Some(LcgRecovery { parameters, state });
+        }
+    }
+    found.ok_or_else(|| "no increment in the range produces these outputs".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use prng::SeedablePrng;
+    use prng::lcg::{Lcg, LcgParameters, GLIBC_TYPE_0, JAVA_RANDOM, MSVC_RAND, MsvcRand,
+                    recover_state, recover_increment};
+    use prng::seed_search::find_seeds;
+
+    #[test]
+    fn reference_outputs() {
+        assert_eq!(Lcg::glibc(1).take(1), vec![1_103_527_590]);
+        assert_eq!(Lcg::glibc(0).take(1), vec![1_103_527_590]);
+        assert_eq!(Lcg::msvc(1).take(4), vec![41, 18467, 6334, 26500]);
+        // new Random(42).nextInt()
+        assert_eq!(Lcg::java(42).next_output() as u32 as i32, -1_170_105_035);
+    }
+
+    fn check_recovery(mut original: Lcg, observed: usize) {
+        let outputs = original.take(observed);
+        let recovery = recover_state(original.parameters(), &outputs).unwrap();
+        assert_eq!(recovery.predict(20), original.take(20));
+    }
+
+    #[test]
+    fn known_parameters() {
+        check_recovery(Lcg::glibc(49), 2);
+        check_recovery(Lcg::java(0x0123_4567_89ab), 2);
+        check_recovery(Lcg::msvc(0xdead_beef), 4);
+
+        assert!(recover_state(JAVA_RANDOM, &[1]).is_err());
+        assert!(recover_state(MSVC_RAND, &[1 << 15, 0]).is_err());
+        assert!(recover_state(MSVC_RAND, &[1, 2, 3, 4, 5]).is_err());
+        // 15 bit outputs cannot pin down 16 hidden bits
+        assert!(recover_state(MSVC_RAND, &Lcg::msvc(49).take(2)).is_err());
+        let wide = LcgParameters { output_shift: 32, modulus_bits: 64, ..JAVA_RANDOM };
+        assert!(recover_state(wide, &[1, 2]).is_err());
+    }
+
+    #[test]
+    fn unknown_increment() {
+        let custom = LcgParameters { increment: 1_013_904_223, ..GLIBC_TYPE_0 };
+        let mut original = Lcg::new(custom, 49);
+        let recovery = recover_increment(custom.multiplier, GLIBC_TYPE_0, &original.take(3), 0..1 << 31)
+            .unwrap();
+        assert_eq!(recovery.parameters.increment, custom.increment & custom.state_mask());
+        assert_eq!(recovery.predict(10), original.take(10));
+
+        let custom = LcgParameters { increment: 2_531_049, ..MSVC_RAND };
+        let mut original = Lcg::new(custom, 49);
+        let outputs = original.take(5);
+        let recovery = recover_increment(custom.multiplier, MSVC_RAND, &outputs, 2_531_000..2_531_100)
+            .unwrap();
+        assert_eq!(recovery.parameters.increment, custom.increment);
+        assert_eq!(recovery.predict(10), original.take(10));
+        assert!(recover_increment(custom.multiplier, MSVC_RAND, &outputs, 0..10).is_err());
+    }
+
+    #[test]
+    fn ambiguous_increment() {
+        let small = LcgParameters {
+            multiplier: 25_173,
+            increment: 13_849,
+            modulus_bits: 16,
+            output_shift: 4,
+            output_bits: 8,
+        };
+        let outputs = Lcg::new(small, 49).take(10);
+        // With three outputs the increment 14445 fits as well
+        let error = recover_increment(small.multiplier, small, &outputs[..3], 13_800..14_500).unwrap_err();
+        assert!(error.contains("more are needed"));
+        let recovery = recover_increment(small.multiplier, small, &outputs, 13_800..14_500).unwrap();
+        assert_eq!(recovery.parameters.increment, small.increment);
+        assert_eq!(recovery.predict(5), Lcg::new(small, 49).take(15)[10..].to_vec());
+    }
+
+    #[test]
+    fn seed_search() {
+        let outputs = MsvcRand::from_seed(12_345).0.take(3);
+        assert_eq!(find_seeds::<MsvcRand>(&outputs, 10_000..20_000, 4), vec![12_345]);
+    }
+}
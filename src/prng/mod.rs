@@ -0,0 +1,15 @@
+// Pseudorandom number generators as used by real software, for attacking rather than for
+// generating anything that needs to be unpredictable.
+
+pub mod lcg;
+pub mod mt19937;
+pub mod mt19937_cipher;
+pub mod mt19937_partial;
+pub mod seed_search;
+
+// A generator that is fully determined by an integer seed, so its seed can be searched for.
+// Seeds wider than the generator takes are truncated, and outputs are widened to 64 bits.
+pub trait SeedablePrng {
+    fn from_seed(seed: u64) -> Self;
+    fn next_output(&mut self) -> u64;
+}
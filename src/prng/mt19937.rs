@@ -0,0 +1,330 @@
+use prng::SeedablePrng;
+
+// The Mersenne Twister, following Matsumoto and Nishimura's reference implementations
+// mt19937ar.c and mt19937-64.c. Its state is a few hundred words that are regenerated all at
+// once ("twisted") and then put through an invertible tempering function one at a time.
+
+pub const STATE_SIZE: usize = 624;
+const SHIFT_SIZE: usize = 397;
+const MATRIX_A: u32 = 0x9908_b0df;
+const UPPER_MASK: u32 = 0x8000_0000;
+const LOWER_MASK: u32 = 0x7fff_ffff;
+
+// The default seed of the reference implementation and of C++'s std::mt19937
+pub const DEFAULT_SEED: u32 = 5489;
+
+#[derive(Clone)]
+pub struct Mt19937 {
+    state: [u32; STATE_SIZE],
+    index: usize,
+}
+
+impl Mt19937 {
+    // init_genrand
+    pub fn new(seed: u32) -> Mt19937 {
+        let mut state = [0u32; STATE_SIZE];
+        state[0] = seed;
+        for i in 1..STATE_SIZE {
+            let previous = state[i - 1];
+            state[i] = 1_812_433_253u32.wrapping_mul(previous ^ (previous >> 30)).wrapping_add(i as u32);
+        }
+        Mt19937 { state, index: STATE_SIZE }
+    }
+
+    // init_by_array, which mixes a key of any length into the state
+    pub fn from_array(key: &[u32]) -> Mt19937 {
+        let mut mt = Mt19937::new(19_650_218);
+        let state = &mut mt.state;
+        let (mut i, mut j) = (1, 0);
+        for _ in 0..STATE_SIZE.max(key.len()) {
+            let previous = state[i - 1];
+            state[i] = (state[i] ^ (previous ^ (previous >> 30)).wrapping_mul(1_664_525))
+                .wrapping_add(key.get(j).cloned().unwrap_or(0))
+                .wrapping_add(j as u32);
+            i += 1;
+            j += 1;
+            if i >= STATE_SIZE {
+                state[0] = state[STATE_SIZE - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..STATE_SIZE - 1 {
+            let previous = state[i - 1];
+            state[i] = (state[i] ^ (previous ^ (previous >> 30)).wrapping_mul(1_566_083_941))
+                .wrapping_sub(i as u32);
+            i += 1;
+            if i >= STATE_SIZE {
+                state[0] = state[STATE_SIZE - 1];
+                i = 1;
+            }
+        }
+        // Ensures the state is not all zero
+        state[0] = 0x8000_0000;
+        mt
+    }
+
+    // A generator whose next outputs temper state[index..], twisting once those run out.
+    pub fn from_state(state: [u32; STATE_SIZE], index: usize) -> Mt19937 {
+        Mt19937 { state, index: index.min(STATE_SIZE) }
+    }
+
+    fn twist(&mut self) {
+        for i in 0..STATE_SIZE {
+            let y = (self.state[i] & UPPER_MASK) | (self.state[(i + 1) % STATE_SIZE] & LOWER_MASK);
+            let mag = if y & 1 == 1 { MATRIX_A } else { 0 };
+            self.state[i] = self.state[(i + SHIFT_SIZE) % STATE_SIZE] ^ (y >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+
+    // genrand_int32
+    pub fn next_u32(&mut self) -> u32 {
+        if self.index >= STATE_SIZE {
+            self.twist();
+        }
+        let y = self.state[self.index];
+        self.index += 1;
+        temper(y)
+    }
+}
+
+pub fn temper(mut y: u32) -> u32 {
+    y ^= y >> 11;
+    y ^= (y << 7) & 0x9d2c_5680;
+    y ^= (y << 15) & 0xefc6_0000;
+    y ^ (y >> 18)
+}
+
+impl SeedablePrng for Mt19937 {
+    fn from_seed(seed: u64) -> Mt19937 {
+        Mt19937::new(seed as u32)
+    }
+
+    fn next_output(&mut self) -> u64 {
+        self.next_u32() as u64
+    }
+}
+
+// Inverts y ^= y >> shift. Each pass fixes another shift bits, from the top down.
+fn undo_right_shift_xor(y: u32, shift: u32) -> u32 {
+    let mut x = y;
+    for _ in 0..32 / shift {
+        x = y ^ (x >> shift);
+    }
+    x
+}
+
+// Inverts y ^= (y << shift) & mask, fixing shift more bits from the bottom up on each pass.
+fn undo_left_shift_xor_and(y: u32, shift: u32, mask: u32) -> u32 {
+    let mut x = y;
+    for _ in 0..32 / shift {
+        x = y ^ ((x << shift) & mask);
+    }
+    x
+}
+
+// Recovers the state word an output was tempered from.
+pub fn untemper(y: u32) -> u32 {
+    let y = undo_right_shift_xor(y, 18);
+    let y = undo_left_shift_xor_and(y, 15, 0xefc6_0000);
+    let y = undo_left_shift_xor_and(y, 7, 0x9d2c_5680);
+    undo_right_shift_xor(y, 11)
+}
+
+// Challenge 23: a generator that continues from 624 consecutive outputs of another. Untempered,
+// they are the last 624 words of its state, which the next twist works from, wherever they
+// fall relative to its twists.
+pub fn clone_from_outputs(outputs: &[u32; STATE_SIZE]) -> Mt19937 {
+    let mut state = [0u32; STATE_SIZE];
+    for (word, &output) in state.iter_mut().zip(outputs.iter()) {
+        *word = untemper(output);
+    }
+    Mt19937::from_state(state, STATE_SIZE)
+}
+
+pub const STATE_SIZE_64: usize = 312;
+const SHIFT_SIZE_64: usize = 156;
+const MATRIX_A_64: u64 = 0xb502_6f5a_a966_19e9;
+const UPPER_MASK_64: u64 = 0xffff_ffff_8000_0000;
+const LOWER_MASK_64: u64 = 0x7fff_ffff;
+
+#[derive(Clone)]
+pub struct Mt19937_64 {
+    state: [u64; STATE_SIZE_64],
+    index: usize,
+}
+
+impl Mt19937_64 {
+    // init_genrand64
+    pub fn new(seed: u64) -> Mt19937_64 {
+        let mut state = [0u64; STATE_SIZE_64];
+        state[0] = seed;
+        for i in 1..STATE_SIZE_64 {
+            let previous = state[i - 1];
+            state[i] = 6_364_136_223_846_793_005u64.wrapping_mul(previous ^ (previous >> 62))
+                .wrapping_add(i as u64);
+        }
+        Mt19937_64 { state, index: STATE_SIZE_64 }
+    }
+
+    // init_by_array64
+    pub fn from_array(key: &[u64]) -> Mt19937_64 {
+        let mut mt = Mt19937_64::new(19_650_218);
+        let state = &mut mt.state;
+        let (mut i, mut j) = (1, 0);
+        for _ in 0..STATE_SIZE_64.max(key.len()) {
+            let previous = state[i - 1];
+            state[i] = (state[i] ^ (previous ^ (previous >> 62)).wrapping_mul(3_935_559_000_370_003_845))
+                .wrapping_add(key.get(j).cloned().unwrap_or(0))
+                .wrapping_add(j as u64);
+            i += 1;
+            j += 1;
+            if i >= STATE_SIZE_64 {
+                state[0] = state[STATE_SIZE_64 - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..STATE_SIZE_64 - 1 {
+            let previous = state[i - 1];
+            state[i] = (state[i] ^ (previous ^ (previous >> 62)).wrapping_mul(2_862_933_555_777_941_757))
+                .wrapping_sub(i as u64);
+            i += 1;
+            if i >= STATE_SIZE_64 {
+                state[0] = state[STATE_SIZE_64 - 1];
+                i = 1;
+            }
+        }
+        state[0] = 1 << 63;
+        mt
+    }
+
+    fn twist(&mut self) {
+        for i in 0..STATE_SIZE_64 {
+            let x = (self.state[i] & UPPER_MASK_64) |
+                    (self.state[(i + 1) % STATE_SIZE_64] & LOWER_MASK_64);
+            let mag = if x & 1 == 1 { MATRIX_A_64 } else { 0 };
+            self.state[i] = self.state[(i + SHIFT_SIZE_64) % STATE_SIZE_64] ^ (x >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+
+    // genrand64_int64
+    pub fn next_u64(&mut self) -> u64 {
+        if self.index >= STATE_SIZE_64 {
+            self.twist();
+        }
+        let mut x = self.state[self.index];
+        self.index += 1;
+        x ^= (x >> 29) & 0x5555_5555_5555_5555;
+        x ^= (x << 17) & 0x71d6_7fff_eda6_0000;
+        x ^= (x << 37) & 0xfff7_eee0_0000_0000;
+        x ^ (x >> 43)
+    }
+}
+
+impl SeedablePrng for Mt19937_64 {
+    fn from_seed(seed: u64) -> Mt19937_64 {
+        Mt19937_64::new(seed)
+    }
+
+    fn next_output(&mut self) -> u64 {
+        self.next_u64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{RngExt, SeedableRng};
+    use rand::rngs::StdRng;
+    use prng::mt19937::{Mt19937, Mt19937_64, DEFAULT_SEED, STATE_SIZE, temper, untemper,
+                        clone_from_outputs};
+
+    #[test]
+    fn mt19937_seeded() {
+        let mut mt = Mt19937::new(DEFAULT_SEED);
+        let first: Vec<u32> = (0..5).map(|_| mt.next_u32()).collect();
+        assert_eq!(first, [3499211612, 581869302, 3890346734, 3586334585, 545404204]);
+        // The C++ standard requires the 10000th output of a default std::mt19937
+        let mut mt = Mt19937::new(DEFAULT_SEED);
+        assert_eq!((0..10000).map(|_| mt.next_u32()).last(), Some(4123659995));
+    }
+
+    #[test]
+    fn mt19937_reference_outputs() {
+        // mt19937ar.out: init_by_array({0x123, 0x234, 0x345, 0x456}) then 1000 outputs
+        let mut mt = Mt19937::from_array(&[0x123, 0x234, 0x345, 0x456]);
+        let outputs: Vec<u32> = (0..1000).map(|_| mt.next_u32()).collect();
+        assert_eq!(outputs[..5], [1067595299, 955945823, 477289528, 4107218783, 4228976476]);
+        assert_eq!(outputs[999], 3460025646);
+        assert_eq!(outputs.iter().map(|&x| x as u64).sum::<u64>(), 2163308063879);
+    }
+
+    #[test]
+    fn mt19937_64_seeded() {
+        let mut mt = Mt19937_64::new(5489);
+        assert_eq!((0..10000).map(|_| mt.next_u64()).last(), Some(9981545732273789042));
+    }
+
+    #[test]
+    fn mt19937_64_reference_outputs() {
+        // mt19937-64.out: init_by_array64({0x12345, 0x23456, 0x34567, 0x45678}) then 1000 outputs
+        let mut mt = Mt19937_64::from_array(&[0x12345, 0x23456, 0x34567, 0x45678]);
+        let outputs: Vec<u64> = (0..1000).map(|_| mt.next_u64()).collect();
+        assert_eq!(outputs[..5],
+                   [7266447313870364031, 4946485549665804864, 16945909448695747420,
+                    16394063075524226720, 4873882236456199058]);
+        assert_eq!(outputs[999], 994412663058993407);
+        assert_eq!(outputs.iter().fold(0u64, |sum, &x| sum.wrapping_add(x)), 11516885302434052501);
+    }
+
+    #[test]
+    fn from_state_resumes() {
+        let mut mt = Mt19937::new(45);
+        for _ in 0..700 {
+            mt.next_u32();
+        }
+        let mut copy = Mt19937::from_state(mt.state, mt.index);
+        for _ in 0..1000 {
+            assert_eq!(copy.next_u32(), mt.next_u32());
+        }
+    }
+
+    #[test]
+    fn untemper_inverts_temper() {
+        let mut rng = StdRng::seed_from_u64(46);
+        for &y in &[0, 1, 0x8000_0000, 0xffff_ffff, 0x9d2c_5680] {
+            assert_eq!(untemper(temper(y)), y);
+        }
+        for _ in 0..10000 {
+            let y: u32 = rng.random();
+            assert_eq!(untemper(temper(y)), y);
+            assert_eq!(temper(untemper(y)), y);
+        }
+    }
+
+    #[test]
+    fn challenge_23() {
+        // Tap at an offset, so the outputs straddle a twist
+        for &skipped in &[0, 1, 300, 623, 1000] {
+            let mut mt = Mt19937::new(23 + skipped as u32);
+            for _ in 0..skipped {
+                mt.next_u32();
+            }
+            let mut outputs = [0u32; STATE_SIZE];
+            for output in outputs.iter_mut() {
+                *output = mt.next_u32();
+            }
+
+            let mut clone = clone_from_outputs(&outputs);
+            for _ in 0..2000 {
+                assert_eq!(clone.next_u32(), mt.next_u32());
+            }
+        }
+    }
+}
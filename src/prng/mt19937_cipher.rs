@@ -0,0 +1,195 @@
+use rand::{Rng, RngExt};
+use error::CryptoError;
+use fixed_xor::{FixedXor, fixed_xor_in_place};
+use oracle;
+use prng::SeedablePrng;
+use prng::mt19937::Mt19937;
+use prng::seed_search;
+use stream_cipher::StreamCipher;
+
+// Challenge 24: the Mersenne Twister used as a stream cipher, taking the low byte of each
+// output as keystream, and the same bytes used as password reset tokens.
+
+pub const TOKEN_LENGTH: usize = 16;
+
+// The keystream bytes of a seeded MT19937.
+#[derive(Clone)]
+pub struct Mt19937Keystream {
+    mt: Mt19937,
+}
+
+impl Mt19937Keystream {
+    pub fn next_byte(&mut self) -> u8 {
+        self.mt.next_u32() as u8
+    }
+
+    // Discards count bytes without keeping them
+    pub fn skip(&mut self, count: u64) {
+        for _ in 0..count {
+            self.next_byte();
+        }
+    }
+
+    pub fn take(&mut self, length: usize) -> Vec<u8> {
+        (0..length).map(|_| self.next_byte()).collect()
+    }
+}
+
+// Outputs are single keystream bytes, so seed searches work on tokens and keystream directly.
+impl SeedablePrng for Mt19937Keystream {
+    fn from_seed(seed: u64) -> Mt19937Keystream {
+        Mt19937Keystream { mt: Mt19937::from_seed(seed) }
+    }
+
+    fn next_output(&mut self) -> u64 {
+        self.next_byte() as u64
+    }
+}
+
+// The cipher itself, keyed by a 16 bit seed.
+pub struct Mt19937Cipher {
+    seed: u16,
+}
+
+impl Mt19937Cipher {
+    pub fn new(seed: u16) -> Mt19937Cipher {
+        Mt19937Cipher { seed }
+    }
+
+    // Keystream has to be generated from the start, so seeking is linear in the offset.
+    pub fn keystream(&self, offset: u64, length: usize) -> Vec<u8> {
+        let mut keystream = Mt19937Keystream::from_seed(self.seed as u64);
+        keystream.skip(offset);
+        keystream.take(length)
+    }
+}
+
+impl StreamCipher for Mt19937Cipher {
+    fn apply_keystream(&self, offset: u64, data: &mut [u8]) -> Result<(), CryptoError> {
+        let keystream = self.keystream(offset, data.len());
+        fixed_xor_in_place(data, &keystream)
+    }
+}
+
+// Encrypts attacker supplied plaintext behind a random number of random bytes.
+pub struct PrefixingMtOracle {
+    cipher: Mt19937Cipher,
+}
+
+impl PrefixingMtOracle {
+    pub fn new<R: Rng>(rng: &mut R) -> PrefixingMtOracle {
+        PrefixingMtOracle { cipher: Mt19937Cipher::new(rng.random()) }
+    }
+
+    pub fn encrypt<R: Rng>(&self, plaintext: &[u8], rng: &mut R) -> Result<Vec<u8>, CryptoError> {
+        let prefix_length = rng.random_range(5..=20);
+        let mut message = oracle::random_bytes(rng, prefix_length);
+        message.extend_from_slice(plaintext);
+        self.cipher.process(&message)
+    }
+}
+
+// Finds the 16 bit seed of a ciphertext whose plaintext ends with known_suffix, by trying all
+// of them against the keystream the suffix exposes. Returns None if no seed fits.
+pub fn recover_seed(ciphertext: &[u8], known_suffix: &[u8]) -> Result<Option<u16>, String> {
+    if known_suffix.is_empty() || known_suffix.len() > ciphertext.len() {
+        return Err(format!("need a known suffix of 1 to {} bytes, got {}",
+                           ciphertext.len(),
+                           known_suffix.len()));
+    }
+    let prefix_length = ciphertext.len() - known_suffix.len();
+    let exposed = ciphertext[prefix_length..].fixed_xor(known_suffix)?;
+
+    Ok((0..=u16::MAX).find(|&seed| {
+        let mut keystream = Mt19937Keystream::from_seed(seed as u64);
+        keystream.skip(prefix_length as u64);
+        exposed.iter().all(|&byte| keystream.next_byte() == byte)
+    }))
+}
+
+// Issues password reset tokens from an MT19937 seeded with the time of the request.
+pub struct ResetTokenOracle;
+
+impl ResetTokenOracle {
+    pub fn reset_token(&self, now: u64) -> Vec<u8> {
+        Mt19937Keystream::from_seed(now).take(TOKEN_LENGTH)
+    }
+}
+
+// Returns the timestamp a token was seeded with if it came from an MT19937 seeded up to window
+// seconds either side of now.
+pub fn find_token_timestamp(token: &[u8], now: u64, window: u64) -> Option<u64> {
+    let outputs: Vec<u64> = token.iter().map(|&byte| byte as u64).collect();
+    let threads = seed_search::available_threads();
+    seed_search::find_timestamp_seeds::<Mt19937Keystream>(&outputs, now, window, threads)
+        .into_iter()
+        .next()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{RngExt, SeedableRng};
+    use rand::rngs::StdRng;
+    use oracle;
+    use prng::mt19937::Mt19937;
+    use prng::mt19937_cipher::{Mt19937Cipher, PrefixingMtOracle, ResetTokenOracle, TOKEN_LENGTH,
+                               recover_seed, find_token_timestamp};
+    use stream_cipher::StreamCipher;
+
+    #[test]
+    fn keystream_is_low_bytes() {
+        let mut mt = Mt19937::new(1234);
+        let expected: Vec<u8> = (0..40).map(|_| mt.next_u32() as u8).collect();
+        let cipher = Mt19937Cipher::new(1234);
+        assert_eq!(cipher.keystream(0, 40), expected);
+        assert_eq!(cipher.keystream(25, 15), &expected[25..]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let cipher = Mt19937Cipher::new(48);
+        let plaintext = b"Now that the party is jumping";
+        let ciphertext = cipher.process(plaintext).unwrap();
+        assert_ne!(&ciphertext[..], &plaintext[..]);
+        assert_eq!(cipher.process(&ciphertext).unwrap(), plaintext);
+
+        let mut tail = ciphertext[10..].to_vec();
+        cipher.apply_keystream(10, &mut tail).unwrap();
+        assert_eq!(&tail[..], &plaintext[10..]);
+    }
+
+    #[test]
+    fn challenge_24_seed() {
+        let mut rng = StdRng::seed_from_u64(48);
+        let oracle = PrefixingMtOracle::new(&mut rng);
+        let known = [b'A'; 14];
+        // Replays the oracle's choice of key and prefix
+        let mut replay = StdRng::seed_from_u64(48);
+        let _key: u16 = replay.random();
+        let prefix_length = replay.random_range(5..=20);
+        let mut expected = oracle::random_bytes(&mut replay, prefix_length);
+        expected.extend_from_slice(&known);
+
+        let ciphertext = oracle.encrypt(&known, &mut rng).unwrap();
+        assert_eq!(ciphertext.len(), expected.len());
+        let seed = recover_seed(&ciphertext, &known).unwrap().unwrap();
+        assert_eq!(Mt19937Cipher::new(seed).process(&ciphertext).unwrap(), expected);
+
+        assert!(recover_seed(&ciphertext, &[]).is_err());
+        assert!(recover_seed(&known[..4], &known).is_err());
+    }
+
+    #[test]
+    fn challenge_24_token() {
+        let mut rng = StdRng::seed_from_u64(48);
+        let now = 1_500_000_000;
+        let issued_at = now - rng.random_range(0..300);
+        let token = ResetTokenOracle.reset_token(issued_at);
+        assert_eq!(token.len(), TOKEN_LENGTH);
+        assert_eq!(find_token_timestamp(&token, now, 300), Some(issued_at));
+        assert_eq!(find_token_timestamp(&token, now + 1000, 300), None);
+
+        let random_token = oracle::random_bytes(&mut rng, TOKEN_LENGTH);
+        assert_eq!(find_token_timestamp(&random_token, now, 300), None);
+    }
+}
@@ -0,0 +1,307 @@
+use prng::mt19937::{self, Mt19937, STATE_SIZE};
+
+// Cloning MT19937 from outputs of which only some bits were seen, such as tokens that keep the
+// top 16 bits of each output, or outputs that went unobserved altogether. Everything in the
+// generator is linear over GF(2): twisting and tempering only XOR, shift and mask bits. So the
+// unknown bits of the first 624 state words are variables, each later state word and output
+// bit is a known XOR of variables, and every observed output bit is a linear equation.
+// There is a variable per unknown bit of the first 624 words, and the cost of solving grows
+// quickly with them, so this suits outputs that are mostly, not entirely, unknown.
+
+const SHIFT_SIZE: usize = 397;
+const MATRIX_A: u32 = 0x9908_b0df;
+
+// One output: the bits set in known_bits are those of value that were observed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialOutput {
+    pub value: u32,
+    pub known_bits: u32,
+}
+
+impl PartialOutput {
+    pub fn known(value: u32) -> PartialOutput {
+        PartialOutput { value, known_bits: 0xffff_ffff }
+    }
+
+    pub fn unknown() -> PartialOutput {
+        PartialOutput { value: 0, known_bits: 0 }
+    }
+}
+
+// An affine combination of variables over GF(2): the XOR of the variables set in terms, XOR
+// constant.
+#[derive(Clone)]
+struct Affine {
+    terms: Vec<u64>,
+    constant: bool,
+}
+
+impl Affine {
+    fn constant(words: usize, constant: bool) -> Affine {
+        Affine { terms: vec![0; words], constant }
+    }
+
+    fn variable(words: usize, variable: usize) -> Affine {
+        let mut affine = Affine::constant(words, false);
+        affine.terms[variable / 64] |= 1 << (variable % 64);
+        affine
+    }
+
+    fn xor(&mut self, other: &Affine) {
+        for (a, b) in self.terms.iter_mut().zip(&other.terms) {
+            *a ^= b;
+        }
+        self.constant ^= other.constant;
+    }
+
+    fn has(&self, variable: usize) -> bool {
+        self.terms[variable / 64] >> (variable % 64) & 1 == 1
+    }
+
+    // Parity of the variables in both self and the bitset other
+    fn parity_with(&self, other: &[u64]) -> bool {
+        self.terms.iter().zip(other).map(|(a, b)| (a & b).count_ones()).sum::<u32>() % 2 == 1
+    }
+
+    fn lowest_variable_from(&self, start: usize) -> Option<usize> {
+        let mut word = start / 64;
+        let mut bits = self.terms.get(word)? & (!0u64 << (start % 64));
+        loop {
+            if bits != 0 {
+                return Some(word * 64 + bits.trailing_zeros() as usize);
+            }
+            word += 1;
+            bits = *self.terms.get(word)?;
+        }
+    }
+}
+
+// A state word as an affine form per bit, least significant first
+type Word = Vec<Affine>;
+
+// Linear equations in echelon form: pivots[v] is an equation whose lowest variable is v.
+struct System {
+    pivots: Vec<Option<Affine>>,
+}
+
+impl System {
+    // Eliminates every variable of the affine form that has a pivot, leaving only free ones.
+    fn reduce(&self, affine: &mut Affine) {
+        let mut next = affine.lowest_variable_from(0);
+        while let Some(variable) = next {
+            if let Some(ref pivot) = self.pivots[variable] {
+                affine.xor(pivot);
+            }
+            next = affine.lowest_variable_from(variable + 1);
+        }
+    }
+
+    // Adds the equation affine = value, failing if it contradicts those already added.
+    fn add(&mut self, mut affine: Affine, value: bool) -> Result<(), String> {
+        affine.constant ^= value;
+        self.reduce(&mut affine);
+        match affine.lowest_variable_from(0) {
+            Some(variable) => self.pivots[variable] = Some(affine),
+            None if affine.constant => return Err("Outputs are inconsistent with MT19937".to_owned()),
+            None => {}
+        }
+        Ok(())
+    }
+
+    // Brings the equations to reduced echelon form, so each pivot equation has no other pivot
+    // variable in it, and its constant is its variable's value when free variables are zero.
+    fn back_substitute(&mut self) {
+        for variable in (0..self.pivots.len()).rev() {
+            if let Some(mut pivot) = self.pivots[variable].take() {
+                let mut next = pivot.lowest_variable_from(variable + 1);
+                while let Some(other) = next {
+                    if let Some(ref other_pivot) = self.pivots[other] {
+                        pivot.xor(other_pivot);
+                    }
+                    next = pivot.lowest_variable_from(other + 1);
+                }
+                self.pivots[variable] = Some(pivot);
+            }
+        }
+    }
+}
+
+// tempering_masks[j] has bit b set when state bit b contributes to output bit j
+fn tempering_masks() -> [u32; 32] {
+    let mut masks = [0u32; 32];
+    for b in 0..32 {
+        let tempered = mt19937::temper(1 << b);
+        for (j, mask) in masks.iter_mut().enumerate() {
+            if tempered >> j & 1 == 1 {
+                *mask |= 1 << b;
+            }
+        }
+    }
+    masks
+}
+
+// The word the twist makes from x[k], x[k + 1] and x[k + 397]
+fn twist(first: &Word, second: &Word, shifted: &Word) -> Word {
+    // y takes the top bit of the first word and the rest of the second
+    let y = |j: usize| if j == 31 { &first[31] } else { &second[j] };
+    (0..32)
+        .map(|j| {
+            let mut bit = shifted[j].clone();
+            if j < 31 {
+                bit.xor(y(j + 1));
+            }
+            if MATRIX_A >> j & 1 == 1 {
+                bit.xor(y(0));
+            }
+            bit
+        })
+        .collect()
+}
+
+// Clones a generator from 624 or more consecutive outputs of which only some bits are known.
+// The clone continues from the last output, and is only returned if the outputs determine
+// all of the state it needs.
+pub fn clone_from_partial_outputs(outputs: &[PartialOutput]) -> Result<Mt19937, String> {
+    if outputs.len() < STATE_SIZE {
+        return Err(format!("Need at least {} outputs, got {}", STATE_SIZE, outputs.len()));
+    }
+
+    // Words with every output bit known are untempered directly; the rest get variables
+    let partial_words = outputs[..STATE_SIZE].iter().filter(|o| o.known_bits != 0xffff_ffff).count();
+    let variable_count = 32 * partial_words;
+    let words = variable_count.div_ceil(64);
+
+    let mut next_variable = 0;
+    let mut window: Vec<Word> = outputs[..STATE_SIZE].iter()
+        .map(|output| {
+            if output.known_bits == 0xffff_ffff {
+                let value = mt19937::untemper(output.value);
+                (0..32).map(|b| Affine::constant(words, value >> b & 1 == 1)).collect()
+            } else {
+                next_variable += 32;
+                (next_variable - 32..next_variable).map(|v| Affine::variable(words, v)).collect()
+            }
+        })
+        .collect();
+
+    let masks = tempering_masks();
+    let mut system = System { pivots: vec![None; variable_count] };
+    for (i, output) in outputs.iter().enumerate() {
+        if i >= STATE_SIZE {
+            let word = twist(&window[0], &window[1], &window[SHIFT_SIZE]);
+            window.remove(0);
+            window.push(word);
+        }
+        let word = &window[i.min(STATE_SIZE - 1)];
+        for j in (0..32).filter(|&j| output.known_bits >> j & 1 == 1) {
+            let mut bit = Affine::constant(words, false);
+            for b in (0..32).filter(|&b| masks[j] >> b & 1 == 1) {
+                bit.xor(&word[b]);
+            }
+            system.add(bit, output.value >> j & 1 == 1)?;
+        }
+    }
+
+    system.back_substitute();
+    let mut values = vec![0u64; words];
+    // For each free variable, the variables whose values would change with it
+    let mut free_dependents = Vec::new();
+    for variable in 0..variable_count {
+        match system.pivots[variable] {
+            Some(ref pivot) if pivot.constant => values[variable / 64] |= 1 << (variable % 64),
+            Some(_) => {}
+            None => {
+                let mut dependents = Affine::variable(words, variable).terms;
+                for (other, pivot) in system.pivots.iter().enumerate() {
+                    if pivot.as_ref().is_some_and(|p| p.has(variable)) {
+                        dependents[other / 64] |= 1 << (other % 64);
+                    }
+                }
+                free_dependents.push(dependents);
+            }
+        }
+    }
+
+    let mut state = [0u32; STATE_SIZE];
+    for (i, (word, symbolic)) in state.iter_mut().zip(&window).enumerate() {
+        for (b, bit) in symbolic.iter().enumerate() {
+            // The next twist only uses the top bit of the oldest word
+            let needed = i > 0 || b == 31;
+            if needed && free_dependents.iter().any(|dependents| bit.parity_with(dependents)) {
+                return Err("Outputs do not determine the generator's state".to_owned());
+            }
+            if bit.parity_with(&values) != bit.constant {
+                *word |= 1 << b;
+            }
+        }
+    }
+    Ok(Mt19937::from_state(state, STATE_SIZE))
+}
+
+#[cfg(test)]
+mod tests {
+    use prng::mt19937::{Mt19937, STATE_SIZE};
+    use prng::mt19937_partial::{PartialOutput, clone_from_partial_outputs};
+
+    fn check_clone(mut original: Mt19937, mut clone: Mt19937) {
+        for _ in 0..1000 {
+            assert_eq!(clone.next_u32(), original.next_u32());
+        }
+    }
+
+    #[test]
+    fn full_outputs() {
+        let mut mt = Mt19937::new(46);
+        let outputs: Vec<PartialOutput> = (0..STATE_SIZE).map(|_| PartialOutput::known(mt.next_u32())).collect();
+        check_clone(mt, clone_from_partial_outputs(&outputs).unwrap());
+    }
+
+    #[test]
+    fn truncated_outputs() {
+        // Only the top 16 bits of a hundred outputs seen, as if they were short tokens
+        let mut mt = Mt19937::new(4646);
+        let outputs: Vec<PartialOutput> = (0..850)
+            .map(|i| {
+                let output = mt.next_u32();
+                if (500..600).contains(&i) {
+                    PartialOutput { value: output & 0xffff_0000, known_bits: 0xffff_0000 }
+                } else {
+                    PartialOutput::known(output)
+                }
+            })
+            .collect();
+        check_clone(mt, clone_from_partial_outputs(&outputs).unwrap());
+    }
+
+    #[test]
+    fn missing_outputs() {
+        // Outputs nobody saw, and bits scattered through others
+        let mut mt = Mt19937::new(464646);
+        let outputs: Vec<PartialOutput> = (0..900)
+            .map(|i| {
+                let output = mt.next_u32();
+                match i {
+                    100..=139 => PartialOutput::unknown(),
+                    400..=449 => PartialOutput { value: output & 0x0f0f_f00f, known_bits: 0x0f0f_f00f },
+                    _ => PartialOutput::known(output),
+                }
+            })
+            .collect();
+        check_clone(mt, clone_from_partial_outputs(&outputs).unwrap());
+    }
+
+    #[test]
+    fn underdetermined() {
+        // Unseen outputs that nothing later depends on enough to pin down
+        let mut mt = Mt19937::new(46);
+        let mut outputs: Vec<PartialOutput> = (0..STATE_SIZE).map(|_| PartialOutput::known(mt.next_u32())).collect();
+        outputs[300] = PartialOutput::unknown();
+        assert!(clone_from_partial_outputs(&outputs).is_err());
+        assert!(clone_from_partial_outputs(&outputs[..100]).is_err());
+
+        // Outputs no generator could produce
+        let mut outputs: Vec<PartialOutput> = (0..700).map(|_| PartialOutput::known(mt.next_u32())).collect();
+        outputs[650].value ^= 1;
+        assert!(clone_from_partial_outputs(&outputs).is_err());
+    }
+}
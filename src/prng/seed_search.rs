@@ -0,0 +1,111 @@
+use std::ops::Range;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use prng::SeedablePrng;
+
+// Recovering the seed of a generator from what it produced, by trying every seed in a range.
+// Seeds taken from the clock make the range small: a timestamp in seconds from a window of
+// minutes or hours either side of when the outputs were seen.
+
+// Seconds since the Unix epoch, the usual choice of seed this is meant to undo.
+pub fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// Threads to search with when the caller has no preference.
+pub fn available_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+fn produces<G: SeedablePrng>(seed: u64, outputs: &[u64]) -> bool {
+    let mut generator = G::from_seed(seed);
+    outputs.iter().all(|&output| generator.next_output() == output)
+}
+
+// Returns every seed in seeds whose generator starts with outputs, in increasing order. The
+// range is split between threads, which each seed their own generators.
+pub fn find_seeds<G: SeedablePrng>(outputs: &[u64], seeds: Range<u64>, threads: usize) -> Vec<u64> {
+    if outputs.is_empty() || seeds.start >= seeds.end {
+        return Vec::new();
+    }
+    let threads = threads.max(1) as u64;
+    let chunk_size = (seeds.end - seeds.start).div_ceil(threads);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|i| seeds.start.saturating_add(i * chunk_size))
+            .take_while(|&start| start < seeds.end)
+            .map(|start| {
+                let chunk = start..start.saturating_add(chunk_size).min(seeds.end);
+                scope.spawn(move || chunk.filter(|&seed| produces::<G>(seed, outputs)).collect::<Vec<u64>>())
+            })
+            .collect();
+        handles.into_iter()
+            .flat_map(|handle| handle.join().expect("seed search thread panicked"))
+            .collect()
+    })
+}
+
+// Challenge 22: finds timestamp seeds up to window seconds before or after now.
+pub fn find_timestamp_seeds<G: SeedablePrng>(outputs: &[u64], now: u64, window: u64, threads: usize)
+                                             -> Vec<u64> {
+    find_seeds::<G>(outputs, now.saturating_sub(window)..now.saturating_add(window).saturating_add(1), threads)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{RngExt, SeedableRng};
+    use rand::rngs::StdRng;
+    use prng::SeedablePrng;
+    use prng::mt19937::{Mt19937, Mt19937_64};
+    use prng::seed_search::{find_seeds, find_timestamp_seeds, available_threads, unix_time};
+
+    #[test]
+    fn challenge_22() {
+        let mut rng = StdRng::seed_from_u64(47);
+        // Seeded between 40 and 1000 seconds before the output was seen
+        let now = 1_500_000_000;
+        let seed = now - rng.random_range(40..1000);
+        let output = Mt19937::from_seed(seed).next_output();
+
+        let seeds = find_timestamp_seeds::<Mt19937>(&[output], now, 1000, available_threads());
+        assert_eq!(seeds, vec![seed]);
+        assert!(unix_time() > now);
+    }
+
+    #[test]
+    fn threads_split_range() {
+        let outputs: Vec<u64> = {
+            let mut mt = Mt19937_64::from_seed(1234);
+            (0..3).map(|_| mt.next_output()).collect()
+        };
+        for threads in 1..6 {
+            assert_eq!(find_seeds::<Mt19937_64>(&outputs, 1000..1237, threads), vec![1234]);
+            assert_eq!(find_seeds::<Mt19937_64>(&outputs, 1234..1235, threads), vec![1234]);
+            assert_eq!(find_seeds::<Mt19937_64>(&outputs, 1235..2000, threads), vec![]);
+        }
+        assert_eq!(find_seeds::<Mt19937_64>(&outputs, 5..5, 4), vec![]);
+        assert_eq!(find_seeds::<Mt19937_64>(&[], 0..10, 4), vec![]);
+        assert_eq!(find_timestamp_seeds::<Mt19937_64>(&[], 10, u64::MAX, 4), vec![]);
+    }
+
+    // A generator with few seeds, so that several match one output
+    struct TinyGenerator(u64);
+
+    impl SeedablePrng for TinyGenerator {
+        fn from_seed(seed: u64) -> TinyGenerator {
+            TinyGenerator(seed % 7)
+        }
+
+        fn next_output(&mut self) -> u64 {
+            self.0 = (self.0 * 3 + 1) % 7;
+            self.0
+        }
+    }
+
+    #[test]
+    fn all_matching_seeds() {
+        let output = TinyGenerator::from_seed(2).next_output();
+        assert_eq!(find_seeds::<TinyGenerator>(&[output], 0..30, 3), vec![2, 9, 16, 23]);
+    }
+}
@@ -0,0 +1,361 @@
+use std::f64::consts::{LN_2, SQRT_2};
+use frequency_analysis;
+
+// A quick battery of statistical tests for byte streams, mostly from NIST SP 800-22 plus
+// Marsaglia's birthday spacings. Each test gives a p-value: the probability that a truly random
+// stream would look at least this unusual. Passing proves nothing, but a keystream or token
+// stream that fails at 0.01 is obviously biased.
+
+pub const DEFAULT_SIGNIFICANCE: f64 = 0.01;
+
+// Bits most significant first, one per element
+pub fn to_bits(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1)).collect()
+}
+
+// Whether ones and zeroes are equally common.
+pub fn monobit(bits: &[u8]) -> Option<f64> {
+    if bits.is_empty() {
+        return None;
+    }
+    let ones = bits.iter().filter(|&&bit| bit == 1).count() as f64;
+    let sum = 2.0 * ones - bits.len() as f64;
+    Some(erfc(sum.abs() / (bits.len() as f64).sqrt() / SQRT_2))
+}
+
+// Whether ones are equally common within each block of block_size bits.
+pub fn block_frequency(bits: &[u8], block_size: usize) -> Option<f64> {
+    let blocks = bits.len().checked_div(block_size).unwrap_or(0);
+    if blocks == 0 {
+        return None;
+    }
+    let chi_squared: f64 = bits.chunks_exact(block_size)
+        .map(|block| {
+            let proportion = block.iter().filter(|&&bit| bit == 1).count() as f64 / block_size as f64;
+            (proportion - 0.5).powi(2)
+        })
+        .sum::<f64>() * 4.0 * block_size as f64;
+    Some(igamc(blocks as f64 / 2.0, chi_squared / 2.0))
+}
+
+// Whether runs of identical bits have the lengths they should, i.e. whether bits flip too often
+// or too rarely.
+pub fn runs(bits: &[u8]) -> Option<f64> {
+    if bits.is_empty() {
+        return None;
+    }
+    let n = bits.len() as f64;
+    let proportion = bits.iter().filter(|&&bit| bit == 1).count() as f64 / n;
+    // The test assumes the stream already passes monobit
+    if (proportion - 0.5).abs() >= 2.0 / n.sqrt() {
+        return Some(0.0);
+    }
+    let runs = 1 + bits.windows(2).filter(|pair| pair[0] != pair[1]).count();
+    let spread = proportion * (1.0 - proportion);
+    Some(erfc((runs as f64 - 2.0 * n * spread).abs() / (2.0 * (2.0 * n).sqrt() * spread)))
+}
+
+// Occurrences of every m bit pattern, overlapping and wrapping around the end.
+fn pattern_counts(bits: &[u8], m: usize) -> Vec<usize> {
+    let mut counts = vec![0; 1 << m];
+    for start in 0..bits.len() {
+        let pattern = (0..m).fold(0, |pattern, i| (pattern << 1) | bits[(start + i) % bits.len()] as usize);
+        counts[pattern] += 1;
+    }
+    counts
+}
+
+fn psi_squared(bits: &[u8], m: usize) -> f64 {
+    if m == 0 {
+        return 0.0;
+    }
+    let n = bits.len() as f64;
+    let squares: f64 = pattern_counts(bits, m).iter().map(|&count| (count * count) as f64).sum();
+    (1 << m) as f64 / n * squares - n
+}
+
+// Whether every m bit pattern is equally common. Returns the two p-values for the first and
+// second differences of the pattern statistics.
+pub fn serial(bits: &[u8], m: usize) -> Option<(f64, f64)> {
+    if m < 2 || bits.len() < m {
+        return None;
+    }
+    let psi = [psi_squared(bits, m), psi_squared(bits, m - 1), psi_squared(bits, m - 2)];
+    let delta = psi[0] - psi[1];
+    let delta_squared = psi[0] - 2.0 * psi[1] + psi[2];
+    Some((igamc((1 << (m - 1)) as f64 / 2.0, delta / 2.0),
+          igamc((1 << (m - 2)) as f64 / 2.0, delta_squared / 2.0)))
+}
+
+fn phi(bits: &[u8], m: usize) -> f64 {
+    let n = bits.len() as f64;
+    pattern_counts(bits, m).iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let proportion = count as f64 / n;
+            proportion * proportion.ln()
+        })
+        .sum()
+}
+
+// Whether m + 1 bit patterns are as unpredictable from their first m bits as they should be.
+pub fn approximate_entropy(bits: &[u8], m: usize) -> Option<f64> {
+    if m == 0 || bits.len() <= m {
+        return None;
+    }
+    let entropy = phi(bits, m) - phi(bits, m + 1);
+    let chi_squared = 2.0 * bits.len() as f64 * (LN_2 - entropy);
+    Some(igamc((1 << (m - 1)) as f64, chi_squared / 2.0))
+}
+
+const BIRTHDAY_BYTES: usize = 3;
+const BIRTHDAYS: usize = 512;
+
+// Birthday spacings over 24 bit birthdays, 512 to a year. The spacings between sorted birthdays
+// should repeat a Poisson number of times with mean 512^3 / 2^26 = 2 per year.
+pub fn birthday_spacings(bytes: &[u8]) -> Option<f64> {
+    let years = bytes.len() / (BIRTHDAY_BYTES * BIRTHDAYS);
+    if years == 0 {
+        return None;
+    }
+    let mut repeats = 0;
+    for year in bytes.chunks_exact(BIRTHDAY_BYTES * BIRTHDAYS).take(years) {
+        let mut birthdays: Vec<u32> = year.chunks_exact(BIRTHDAY_BYTES)
+            .map(|day| day.iter().fold(0, |birthday, &byte| (birthday << 8) | byte as u32))
+            .collect();
+        birthdays.sort_unstable();
+        let mut spacings: Vec<u32> = birthdays.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        spacings.push(birthdays[0]);
+        spacings.sort_unstable();
+        repeats += spacings.windows(2).filter(|pair| pair[0] == pair[1]).count();
+    }
+
+    let mean = 2.0 * years as f64;
+    let at_most = igamc(repeats as f64 + 1.0, mean);
+    let at_least = if repeats == 0 { 1.0 } else { igam(repeats as f64, mean) };
+    Some((2.0 * at_most.min(at_least)).min(1.0))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestResult {
+    pub name: &'static str,
+    // None when the stream is too short for the test
+    pub p_value: Option<f64>,
+}
+
+impl TestResult {
+    pub fn passed(&self, significance: f64) -> bool {
+        self.p_value.is_none_or(|p| p >= significance)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RandomnessReport {
+    pub bits: usize,
+    pub entropy_per_byte: f64,
+    pub results: Vec<TestResult>,
+}
+
+impl RandomnessReport {
+    pub fn failures(&self, significance: f64) -> Vec<&TestResult> {
+        self.results.iter().filter(|result| !result.passed(significance)).collect()
+    }
+
+    pub fn passed(&self, significance: f64) -> bool {
+        self.failures(significance).is_empty()
+    }
+}
+
+// Runs every test with parameters scaled to the length of bytes.
+pub fn test_randomness(bytes: &[u8]) -> RandomnessReport {
+    let bits = to_bits(bytes);
+    let log_length = (bits.len().max(1) as f64).log2() as usize;
+    // SP 800-22 asks for at least 20 bits a block and fewer than 100 blocks
+    let block_size = 128.max(bits.len() / 99 + 1);
+    let serial_length = log_length.saturating_sub(3).min(16);
+    let entropy_length = log_length.saturating_sub(6).min(10);
+    let (serial_first, serial_second) = match serial(&bits, serial_length) {
+        Some((first, second)) => (Some(first), Some(second)),
+        None => (None, None),
+    };
+    // SP 800-22 recommends at least 100 bits for the rest
+    let long_enough = |p: Option<f64>| p.filter(|_| bits.len() >= 100);
+
+    RandomnessReport {
+        bits: bits.len(),
+        entropy_per_byte: frequency_analysis::shannon_entropy(bytes),
+        results: vec![
+            TestResult { name: "monobit", p_value: long_enough(monobit(&bits)) },
+            TestResult { name: "block frequency", p_value: block_frequency(&bits, block_size) },
+            TestResult { name: "runs", p_value: long_enough(runs(&bits)) },
+            TestResult { name: "serial", p_value: long_enough(serial_first) },
+            TestResult { name: "serial second difference", p_value: long_enough(serial_second) },
+            TestResult {
+                name: "approximate entropy",
+                p_value: long_enough(approximate_entropy(&bits, entropy_length)),
+            },
+            TestResult { name: "birthday spacings", p_value: birthday_spacings(bytes) },
+        ],
+    }
+}
+
+// Complementary error function, with fractional error below 1.2e-7 (Numerical Recipes' erfcc).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let coefficients = [-1.265_512_23, 1.000_023_68, 0.374_091_96, 0.096_784_18, -0.186_288_06,
+                        0.278_868_07, -1.135_203_98, 1.488_515_87, -0.822_152_23, 0.170_872_77];
+    let polynomial = coefficients.iter().rev().fold(0.0, |sum, &c| sum * t + c);
+    let result = t * (-z * z + polynomial).exp();
+    if x >= 0.0 { result } else { 2.0 - result }
+}
+
+// Lanczos approximation to ln Γ(x) for x > 0.
+fn ln_gamma(x: f64) -> f64 {
+    let coefficients = [76.180_091_729_471_46, -86.505_320_329_416_77, 24.014_098_240_830_91,
+                        -1.231_739_572_450_155, 0.001_208_650_973_866_179, -0.000_005_395_239_384_953];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let series = coefficients.iter().enumerate()
+        .fold(1.000_000_000_190_015, |sum, (i, &c)| sum + c / (x + 1.0 + i as f64));
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+const GAMMA_ITERATIONS: usize = 1000;
+const GAMMA_EPSILON: f64 = 1e-15;
+
+// Regularised lower incomplete gamma function P(a, x)
+fn igam(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= a + 1.0 {
+        return 1.0 - igamc(a, x);
+    }
+    let mut term = 1.0 / a;
+    let mut sum = term;
+    for n in 1..GAMMA_ITERATIONS {
+        term *= x / (a + n as f64);
+        sum += term;
+        if term.abs() < sum.abs() * GAMMA_EPSILON {
+            break;
+        }
+    }
+    sum * (-x + a * x.ln() - ln_gamma(a)).exp()
+}
+
+// Regularised upper incomplete gamma function Q(a, x), by Lentz's continued fraction
+fn igamc(a: f64, x: f64) -> f64 {
+    if x < a + 1.0 {
+        return 1.0 - igam(a, x);
+    }
+    let tiny = 1e-300;
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / tiny;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..GAMMA_ITERATIONS {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < tiny {
+            d = tiny;
+        }
+        c = b + an / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < GAMMA_EPSILON {
+            break;
+        }
+    }
+    (-x + a * x.ln() - ln_gamma(a)).exp() * h
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use oracle;
+    use prng::lcg::Lcg;
+    use prng::mt19937_cipher::Mt19937Cipher;
+    use randomness::{self, to_bits, monobit, block_frequency, runs, serial, approximate_entropy,
+                     birthday_spacings, test_randomness, DEFAULT_SIGNIFICANCE};
+
+    fn bits(text: &str) -> Vec<u8> {
+        text.bytes().map(|c| c - b'0').collect()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn special_functions() {
+        assert_close(randomness::erfc(0.0), 1.0);
+        assert_close(randomness::erfc(1.0), 0.157_299_207);
+        assert_close(randomness::erfc(-1.0), 1.842_700_793);
+        assert_close(randomness::ln_gamma(5.0), 24f64.ln());
+        assert_close(randomness::igamc(1.0, 2.0), (-2f64).exp());
+        assert_close(randomness::igamc(3.0, 1.0) + randomness::igam(3.0, 1.0), 1.0);
+        assert_close(randomness::igam(3.0, 10.0), 1.0 - 61.0 * (-10f64).exp());
+    }
+
+    // The worked examples of SP 800-22 section 2
+    #[test]
+    fn sp_800_22_examples() {
+        assert_eq!(to_bits(&[0xa5]), bits("10100101"));
+        assert_close(monobit(&bits("1011010101")).unwrap(), 0.527_089);
+        assert_close(block_frequency(&bits("0110011010"), 3).unwrap(), 0.801_252);
+        assert_close(runs(&bits("1001101011")).unwrap(), 0.147_232);
+        let (first, second) = serial(&bits("0011011101"), 3).unwrap();
+        assert_close(first, 0.808_792);
+        assert_close(second, 0.670_320);
+        assert_close(approximate_entropy(&bits("0100110101"), 3).unwrap(), 0.261_961);
+    }
+
+    #[test]
+    fn short_streams() {
+        let report = test_randomness(&[0x42; 4]);
+        assert!(report.results.iter().all(|result| result.p_value.is_none()));
+        assert!(report.passed(DEFAULT_SIGNIFICANCE));
+        assert_eq!(birthday_spacings(&[0; 1535]), None);
+        assert_eq!(block_frequency(&[1; 10], 0), None);
+    }
+
+    #[test]
+    fn random_streams_pass() {
+        let mut rng = StdRng::seed_from_u64(50);
+        let report = test_randomness(&oracle::random_bytes(&mut rng, 1 << 14));
+        assert_eq!(report.bits, 1 << 17);
+        assert!(report.entropy_per_byte > 7.9);
+        assert!(report.results.iter().all(|result| result.p_value.is_some()));
+        assert!(report.passed(DEFAULT_SIGNIFICANCE), "{:?}", report);
+
+        let keystream = Mt19937Cipher::new(50).keystream(0, 1 << 14);
+        assert!(test_randomness(&keystream).passed(DEFAULT_SIGNIFICANCE));
+    }
+
+    #[test]
+    fn biased_streams_fail() {
+        // One bit in 16 is stuck
+        let mut rng = StdRng::seed_from_u64(50);
+        let stuck: Vec<u8> = oracle::random_bytes(&mut rng, 1 << 14).iter()
+            .enumerate()
+            .map(|(i, &byte)| if i % 2 == 0 { byte | 1 } else { byte })
+            .collect();
+        let report = test_randomness(&stuck);
+        assert!(report.failures(DEFAULT_SIGNIFICANCE).iter().any(|result| result.name == "monobit"));
+
+        // The low bits of a power of two LCG have short periods
+        let mut lcg = Lcg::glibc(50);
+        let low_bytes: Vec<u8> = (0..1 << 14).map(|_| lcg.next_output() as u8).collect();
+        assert!(!test_randomness(&low_bytes).passed(DEFAULT_SIGNIFICANCE));
+
+        // Repeated birthdays give repeated spacings
+        let repeated: Vec<u8> = oracle::random_bytes(&mut rng, 768).repeat(8);
+        assert!(birthday_spacings(&repeated).unwrap() < DEFAULT_SIGNIFICANCE);
+    }
+}
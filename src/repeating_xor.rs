@@ -1,8 +1,10 @@
 use std::ops::BitXor;
+use std::cmp::Ordering;
 use std::collections::btree_map::BTreeMap;
-use std::str::Chars;
-use std::borrow::Borrow;
-use single_byte_xor::SingleByteXorDecodable;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use frequency_analysis;
 use utility;
 
 pub trait RepeatingXorEncodable {
@@ -38,77 +40,262 @@ impl<I : Iterator + Clone> RepeatingXorEncodable for I
     }
 }
 
-impl RepeatingXorDecodable for [u8] {
-    type Output = Result<String, String>;
+// Restricts the bytes a recovered key may be made from.
+pub enum KeyCharset {
+    Any,
+    PrintableAscii,
+    Alphanumeric,
+    Hex,
+    Custom(Vec<u8>),
+}
 
-    fn find_repeating_xor_decode(&self,
-                                 character_frequencies : &BTreeMap<char, f32>)
-                                 -> Result<String, String> {
+impl KeyCharset {
+    pub fn allows(&self, byte: u8) -> bool {
+        match *self {
+            KeyCharset::Any => true,
+            KeyCharset::PrintableAscii => (0x20..0x7f).contains(&byte),
+            KeyCharset::Alphanumeric => (byte as char).is_ascii_alphanumeric(),
+            KeyCharset::Hex => (byte as char).is_ascii_hexdigit(),
+            KeyCharset::Custom(ref bytes) => bytes.contains(&byte),
+        }
+    }
 
-        println!("{:?}", self);
-        // Find smallest edit distances, one of these is likely to be the key length
-        if self.len() < 1 {
-            return Ok(String::new());
+    fn allowed_bytes(&self) -> Vec<u8> {
+        (0..256u16).map(|b| b as u8).filter(|&b| self.allows(b)).collect()
+    }
+}
+
+// Constraints on the key space searched when recovering a repeating XOR key. Lengths are
+// inclusive. Dictionary words are tried as whole keys in addition to the per-column search,
+// which lets short keys be found even when there is too little ciphertext for the column
+// statistics to be meaningful.
+pub struct KeyConstraints {
+    pub charset: KeyCharset,
+    pub dictionary: Vec<Vec<u8>>,
+    pub min_key_length: usize,
+    pub max_key_length: usize,
+}
+
+impl KeyConstraints {
+    pub fn new() -> KeyConstraints {
+        KeyConstraints {
+            charset: KeyCharset::Any,
+            dictionary: Vec::new(),
+            min_key_length: 1,
+            max_key_length: 39,
         }
-        let mut normalised_edit_distance_and_lengths =
-            match utility::find_normalized_edit_distances(self) {
-            Ok(x) => x,
-            Err(e) => return Err(e)
-        };
-        // Got our edit distances, now we can use the top however many as possible keys
-
-        // Create n vectors for each different char in the key
-        // i.e. if the key is CATS, all chars encoded by the C should be in the first vec
-        // A in the second vec, and so on and so forth
-        match normalised_edit_distance_and_lengths.pop() {
-            Some((_, len)) => {
-                let supposed_key_length = len;
-                let mut bit_strings_to_decode : Vec<Vec<u8>> = Vec::new();
-                for _i in 0..supposed_key_length {
-                    bit_strings_to_decode.push(Vec::new());
-                }
+    }
 
-                for (i, byte) in self.iter().enumerate() {
-                    bit_strings_to_decode[i % supposed_key_length].push(*byte);
-                }
+    pub fn allows_key(&self, key: &[u8]) -> bool {
+        key.len() >= self.min_key_length && key.len() <= self.max_key_length &&
+            key.iter().all(|&b| self.charset.allows(b))
+    }
+}
 
-                let mut decoded_strings : Vec<String> = Vec::new();
-                for bit_string in &bit_strings_to_decode {
-                    let bit_string_borrow : &[u8] = bit_string.borrow();
-                    decoded_strings.push(
-                        utility::filter_strings_heuristically(
-                            bit_string_borrow.find_all_single_byte_xor_decodes()
-                        ).remove(0)
-                    );   
-                }
-                
-                let mut decoded_string_chars : Vec<Chars> = decoded_strings.iter().map(|x| x.chars()).collect();
-
-                let mut decode_candidate = String::new();
-
-                let mut creating_decode_candidate = true;
-                while creating_decode_candidate {
-                    for mut char_iter in decoded_string_chars.iter_mut() {
-                        match char_iter.next()  {
-                            None => creating_decode_candidate = false,
-                            Some(c) => decode_candidate.push(c)
-                        }
-                    }
+impl Default for KeyConstraints {
+    fn default() -> KeyConstraints {
+        KeyConstraints::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyCandidate {
+    pub key: Vec<u8>,
+    // Character frequency score of the full decode, higher is better
+    pub score: f32,
+}
+
+// Number of most likely key lengths to attempt per-column decodes for
+const KEY_LENGTHS_TO_TRY: usize = 5;
+// Columns shorter than this are too easy to overfit, so longer key lengths are left to the dictionary
+const MIN_COLUMN_LENGTH: usize = 8;
+
+// Reads a newline separated list of candidate keys, skipping blank lines.
+pub fn load_key_dictionary<P: AsRef<Path>>(path: P) -> Result<Vec<Vec<u8>>, String> {
+    let file = match File::open(path.as_ref()) {
+        Ok(f) => f,
+        Err(e) => {
+            return Err(format!("Could not open key dictionary {}: {}", path.as_ref().display(), e))
+        }
+    };
+    let mut words = Vec::new();
+    for line in BufReader::new(file).lines() {
+        match line {
+            Ok(l) => {
+                let word = l.trim();
+                if !word.is_empty() {
+                    words.push(word.as_bytes().to_vec());
                 }
+            }
+            Err(e) => return Err(format!("Could not read key dictionary: {}", e)),
+        }
+    }
+    Ok(words)
+}
+
+// Reduces a key to its shortest repeating unit, e.g. ICEICE to ICE
+fn shortest_period(key: &[u8]) -> Vec<u8> {
+    for period in 1..key.len() {
+        if key.len().is_multiple_of(period) && (period..key.len()).all(|i| key[i] == key[i - period]) {
+            return key[..period].to_vec();
+        }
+    }
+    key.to_vec()
+}
 
-                println!("{}", decode_candidate);
-                return Ok(decode_candidate);
+// XORs bytes with a repeating key. An empty key leaves the bytes unchanged.
+pub fn apply_repeating_key(bytes: &[u8], key: &[u8]) -> Vec<u8> {
+    if key.is_empty() {
+        return bytes.to_vec();
+    }
+    bytes.iter().zip(key.iter().cycle()).map(|(b, k)| b ^ k).collect()
+}
+
+// Finds the best key byte for every column of a supposed key length, or None if some column
+// has no allowed key byte producing a plausible decode.
+fn find_key_for_length(bytes: &[u8],
+                       key_length: usize,
+                       allowed_key_bytes: &[u8],
+                       character_frequencies: &BTreeMap<char, f32>)
+                       -> Option<Vec<u8>> {
+    // Create n vectors for each different char in the key
+    // i.e. if the key is CATS, all chars encoded by the C should be in the first vec
+    // A in the second vec, and so on and so forth
+    let mut columns: Vec<Vec<u8>> = vec![Vec::new(); key_length];
+    for (i, byte) in bytes.iter().enumerate() {
+        columns[i % key_length].push(*byte);
+    }
+
+    let mut key = Vec::with_capacity(key_length);
+    for column in &columns {
+        let mut best: Option<(u8, f32)> = None;
+        for &key_byte in allowed_key_bytes {
+            let decoded: Vec<u8> = column.iter().map(|b| b ^ key_byte).collect();
+            let score = frequency_analysis::plaintext_score(&decoded, character_frequencies);
+            if let Some(score) = score {
+                match best {
+                    Some((_, best_score)) if best_score >= score => {}
+                    _ => best = Some((key_byte, score)),
+                }
             }
-            None => return Err("Find repeating xor could not find any edit distances \
-                                and couldn't calcuate any key sizes".to_owned())
-        } 
+        }
+        match best {
+            Some((key_byte, _)) => key.push(key_byte),
+            None => return None,
+        }
+    }
+    Some(key)
+}
+
+// Returns the key lengths, most likely first, that find_repeating_xor_keys attacks column by
+// column. This is empty if there is too little ciphertext for column statistics but a
+// dictionary to fall back on.
+pub fn find_repeating_xor_key_lengths(bytes: &[u8],
+                                      constraints: &KeyConstraints)
+                                      -> Result<Vec<usize>, String> {
+    if constraints.min_key_length > constraints.max_key_length {
+        return Err(format!("Invalid key length range: {} to {}",
+                           constraints.min_key_length,
+                           constraints.max_key_length));
+    }
+
+    let max_statistical_key_length =
+        constraints.max_key_length.min(bytes.len() / MIN_COLUMN_LENGTH);
+    if constraints.min_key_length <= max_statistical_key_length {
+        let mut key_lengths = utility::find_key_length_candidates(
+            bytes,
+            constraints.min_key_length,
+            max_statistical_key_length,
+        )?;
+        key_lengths.truncate(KEY_LENGTHS_TO_TRY);
+        Ok(key_lengths)
+    } else if constraints.dictionary.is_empty() {
+        Err(format!("Too little ciphertext ({} bytes) to recover keys of length {} to {} \
+                     without a dictionary",
+                    bytes.len(),
+                    constraints.min_key_length,
+                    constraints.max_key_length))
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+// Returns candidate keys satisfying the constraints, best first. The most likely key lengths
+// are attacked column by column with only the allowed key bytes, then any dictionary words are
+// scored as whole keys.
+pub fn find_repeating_xor_keys(bytes: &[u8],
+                               constraints: &KeyConstraints,
+                               character_frequencies: &BTreeMap<char, f32>)
+                               -> Result<Vec<KeyCandidate>, String> {
+    let mut candidates: Vec<KeyCandidate> = Vec::new();
+
+    let allowed_key_bytes = constraints.charset.allowed_bytes();
+    for key_length in find_repeating_xor_key_lengths(bytes, constraints)? {
+        let key = match find_key_for_length(bytes,
+                                            key_length,
+                                            &allowed_key_bytes,
+                                            character_frequencies) {
+            Some(k) => shortest_period(&k),
+            None => continue,
+        };
+        if key.len() < constraints.min_key_length || candidates.iter().any(|c| c.key == key) {
+            continue;
+        }
+        let decoded = apply_repeating_key(bytes, &key);
+        if let Some(score) = frequency_analysis::plaintext_score(&decoded, character_frequencies) {
+            candidates.push(KeyCandidate { key, score });
+        }
+    }
+
+    for word in &constraints.dictionary {
+        if !constraints.allows_key(word) || candidates.iter().any(|c| &c.key == word) {
+            continue;
+        }
+        let decoded = apply_repeating_key(bytes, word);
+        if let Some(score) = frequency_analysis::plaintext_score(&decoded, character_frequencies) {
+            candidates.push(KeyCandidate { key: word.clone(), score });
+        }
+    }
+
+    candidates.sort_by(|c1, c2| c2.score.partial_cmp(&c1.score).unwrap_or(Ordering::Equal));
+    Ok(candidates)
+}
+
+pub fn find_repeating_xor_key(bytes: &[u8],
+                              constraints: &KeyConstraints,
+                              character_frequencies: &BTreeMap<char, f32>)
+                              -> Result<Vec<u8>, String> {
+    let mut candidates = find_repeating_xor_keys(bytes, constraints, character_frequencies)?;
+    if candidates.is_empty() {
+        return Err("Find repeating xor could not find any key satisfying the key constraints"
+            .to_owned());
+    }
+    Ok(candidates.remove(0).key)
+}
+
+impl RepeatingXorDecodable for [u8] {
+    type Output = Result<String, String>;
+
+    fn find_repeating_xor_decode(&self,
+                                 character_frequencies : &BTreeMap<char, f32>)
+                                 -> Result<String, String> {
+        if self.is_empty() {
+            return Ok(String::new());
+        }
+        let key = find_repeating_xor_key(self, &KeyConstraints::new(), character_frequencies)?;
+        Ok(apply_repeating_key(self, &key).iter().map(|&b| b as char).collect())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::borrow::Borrow;
-    use repeating_xor::{RepeatingXorEncodable, RepeatingXorDecodable};
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::process;
+    use repeating_xor::{RepeatingXorEncodable, RepeatingXorDecodable, KeyCharset, KeyConstraints,
+                        find_repeating_xor_key, find_repeating_xor_keys, load_key_dictionary};
     use frequency_analysis::english_letter_frequencies;
     use rustc_serialize::hex::FromHex;
 
@@ -141,4 +328,62 @@ mod tests {
 
         assert_eq!(decoded_string, plaintext_string);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_key_constraints_restrict_key_bytes() {
+        let plaintext_string = "Burning 'em, if you ain't quick and nimble\nI go crazy when I hear a cymbal";
+        let ciphertext = plaintext_string.as_bytes().iter().repeating_xor_encode("ICE".as_bytes().iter()).unwrap();
+
+        let mut constraints = KeyConstraints::new();
+        constraints.charset = KeyCharset::PrintableAscii;
+        constraints.min_key_length = 2;
+        constraints.max_key_length = 4;
+        let candidates = find_repeating_xor_keys(&ciphertext, &constraints, &english_letter_frequencies()).unwrap();
+        assert_eq!(candidates[0].key, b"ICE".to_vec());
+        assert!(candidates.iter().all(|c| constraints.allows_key(&c.key)));
+
+        // No key made of hex digits decodes every column plausibly
+        constraints.charset = KeyCharset::Hex;
+        let candidates = find_repeating_xor_keys(&ciphertext, &constraints, &english_letter_frequencies()).unwrap();
+        assert!(candidates.iter().all(|c| c.key != b"ICE".to_vec()));
+        assert!(candidates.iter().all(|c| c.key.iter().all(|b| (*b as char).is_ascii_hexdigit())));
+    }
+
+    #[test]
+    fn test_dictionary_fallback_for_short_ciphertext() {
+        let plaintext_string = "Attack at dawn";
+        let ciphertext = plaintext_string.as_bytes().iter().repeating_xor_encode("lemon".as_bytes().iter()).unwrap();
+
+        // Far too short for column statistics on keys of this length
+        let mut constraints = KeyConstraints::new();
+        constraints.min_key_length = 4;
+        constraints.max_key_length = 6;
+        assert!(find_repeating_xor_key(&ciphertext, &constraints, &english_letter_frequencies()).is_err());
+
+        let mut dictionary_path = env::temp_dir();
+        // Unique to this process so concurrent test runs don't share the file
+        dictionary_path.push(format!("matasano_repeating_xor_key_dictionary_{}.txt", process::id()));
+        {
+            let mut dictionary_file = File::create(&dictionary_path).unwrap();
+            dictionary_file.write_all(b"apple\nbanana\n\nlemon\ncherry\nlemonade\n").unwrap();
+        }
+
+        constraints.dictionary = load_key_dictionary(&dictionary_path).unwrap();
+        fs::remove_file(&dictionary_path).unwrap();
+        assert_eq!(constraints.dictionary.len(), 5);
+        let key = find_repeating_xor_key(&ciphertext, &constraints, &english_letter_frequencies()).unwrap();
+        assert_eq!(key, b"lemon".to_vec());
+
+        assert!(load_key_dictionary(env::temp_dir().join("no_such_matasano_dictionary.txt")).is_err());
+    }
+
+    #[test]
+    fn test_long_key_recovery() {
+        let plaintext = include_str!("english_corpus.txt");
+        let key = "Terminator X: Bring the noise";
+        let ciphertext = plaintext.as_bytes().iter().repeating_xor_encode(key.as_bytes().iter()).unwrap();
+
+        let recovered_key = find_repeating_xor_key(&ciphertext, &KeyConstraints::new(), &english_letter_frequencies()).unwrap();
+        assert_eq!(recovered_key, key.as_bytes().to_vec());
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::btree_map::BTreeMap;
+use rustc_serialize::hex::ToHex;
+use rustc_serialize::json::{self, Json, ToJson};
+use repeating_xor::{self, KeyCandidate};
+use single_byte_xor::{SingleByteXorCandidate, SingleByteXorDetection};
+
+// Machine readable results of the cracking functions. Every byte string is given both as hex,
+// which is exact, and as lossy UTF-8, which is readable.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BytesResult {
+    pub hex: String,
+    pub utf8_lossy: String,
+}
+
+impl BytesResult {
+    pub fn new(bytes: &[u8]) -> BytesResult {
+        BytesResult {
+            hex: bytes.to_hex(),
+            utf8_lossy: String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+}
+
+impl ToJson for BytesResult {
+    fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        object.insert("hex".to_owned(), self.hex.to_json());
+        object.insert("utf8_lossy".to_owned(), self.utf8_lossy.to_json());
+        Json::Object(object)
+    }
+}
+
+// Widens an f32 score through its shortest decimal form, so 0.3 is written as 0.3 rather than
+// 0.30000001192092896.
+fn score_json(score: f32) -> Json {
+    score.to_string().parse::<f64>().unwrap_or(score as f64).to_json()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SingleByteXorResult {
+    pub key: u8,
+    pub score: f32,
+    pub plaintext: BytesResult,
+}
+
+impl<'a> From<&'a SingleByteXorCandidate> for SingleByteXorResult {
+    fn from(candidate: &'a SingleByteXorCandidate) -> SingleByteXorResult {
+        SingleByteXorResult {
+            key: candidate.key,
+            score: candidate.score,
+            plaintext: BytesResult::new(&candidate.plaintext),
+        }
+    }
+}
+
+impl ToJson for SingleByteXorResult {
+    fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        object.insert("key".to_owned(), self.key.to_json());
+        object.insert("score".to_owned(), score_json(self.score));
+        object.insert("plaintext".to_owned(), self.plaintext.to_json());
+        Json::Object(object)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatingXorKeyResult {
+    pub key: BytesResult,
+    pub key_size: usize,
+    pub score: f32,
+}
+
+impl<'a> From<&'a KeyCandidate> for RepeatingXorKeyResult {
+    fn from(candidate: &'a KeyCandidate) -> RepeatingXorKeyResult {
+        RepeatingXorKeyResult {
+            key: BytesResult::new(&candidate.key),
+            key_size: candidate.key.len(),
+            score: candidate.score,
+        }
+    }
+}
+
+impl ToJson for RepeatingXorKeyResult {
+    fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        object.insert("key".to_owned(), self.key.to_json());
+        object.insert("key_size".to_owned(), self.key_size.to_json());
+        object.insert("score".to_owned(), score_json(self.score));
+        Json::Object(object)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatingXorResult {
+    // Key sizes attacked column by column, most likely first
+    pub key_size_candidates: Vec<usize>,
+    // Every key found, best first
+    pub keys: Vec<RepeatingXorKeyResult>,
+    // Decode with the best key, if any key was found
+    pub plaintext: Option<BytesResult>,
+}
+
+impl RepeatingXorResult {
+    pub fn new(ciphertext: &[u8],
+               key_size_candidates: Vec<usize>,
+               candidates: &[KeyCandidate])
+               -> RepeatingXorResult {
+        RepeatingXorResult {
+            key_size_candidates,
+            keys: candidates.iter().map(RepeatingXorKeyResult::from).collect(),
+            plaintext: candidates.first().map(|best| {
+                BytesResult::new(&repeating_xor::apply_repeating_key(ciphertext, &best.key))
+            }),
+        }
+    }
+}
+
+impl ToJson for RepeatingXorResult {
+    fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        object.insert("key_size_candidates".to_owned(), self.key_size_candidates.to_json());
+        object.insert("keys".to_owned(), self.keys.to_json());
+        object.insert("plaintext".to_owned(), self.plaintext.to_json());
+        Json::Object(object)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectionResult {
+    // Index of the ciphertext among those searched, e.g. its line number less one
+    pub index: usize,
+    pub ciphertext: BytesResult,
+    pub result: SingleByteXorResult,
+}
+
+impl DetectionResult {
+    pub fn new(ciphertexts: &[Vec<u8>], detection: &SingleByteXorDetection) -> DetectionResult {
+        DetectionResult {
+            index: detection.index,
+            ciphertext: BytesResult::new(&ciphertexts[detection.index]),
+            result: SingleByteXorResult::from(&detection.candidate),
+        }
+    }
+}
+
+impl ToJson for DetectionResult {
+    fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        object.insert("index".to_owned(), self.index.to_json());
+        object.insert("ciphertext".to_owned(), self.ciphertext.to_json());
+        object.insert("result".to_owned(), self.result.to_json());
+        Json::Object(object)
+    }
+}
+
+// Pretty prints any result as JSON with keys in sorted order, so output can be diffed.
+pub fn to_pretty_json<T: ToJson>(result: &T) -> String {
+    json::as_pretty_json(&result.to_json()).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_serialize::json::{Json, ToJson};
+    use repeating_xor::{self, KeyCandidate};
+    use single_byte_xor::{SingleByteXorCandidate, SingleByteXorDetection};
+    use results::{BytesResult, SingleByteXorResult, RepeatingXorResult, DetectionResult,
+                  to_pretty_json};
+
+    #[test]
+    fn bytes_result() {
+        let result = BytesResult::new(&[0x68, 0x69, 0xff]);
+        assert_eq!(result.hex, "6869ff");
+        assert_eq!(result.utf8_lossy, "hi\u{fffd}");
+    }
+
+    #[test]
+    fn single_byte_xor_json() {
+        let candidate = SingleByteXorCandidate { key: 0x58, plaintext: b"hi".to_vec(), score: 0.5 };
+        let json = SingleByteXorResult::from(&candidate).to_json();
+        assert_eq!(json.find("key"), Some(&Json::U64(0x58)));
+        assert_eq!(json.find_path(&["plaintext", "hex"]), Some(&Json::String("6869".to_owned())));
+        assert_eq!(json.find("score").and_then(|s| s.as_f64()), Some(0.5));
+
+        let candidate = SingleByteXorCandidate { key: 0x58, plaintext: b"hi".to_vec(), score: 0.3 };
+        let json = to_pretty_json(&SingleByteXorResult::from(&candidate));
+        assert!(json.contains("\"score\": 0.3\n"), "{}", json);
+    }
+
+    #[test]
+    fn repeating_xor_json() {
+        let ciphertext = repeating_xor::apply_repeating_key(b"hello", b"ICE");
+        let candidates = [KeyCandidate { key: b"ICE".to_vec(), score: 0.25 }];
+        let result = RepeatingXorResult::new(&ciphertext, vec![3, 2], &candidates);
+        assert_eq!(result.plaintext, Some(BytesResult::new(b"hello")));
+
+        let parsed = Json::from_str(&to_pretty_json(&result)).unwrap();
+        assert_eq!(parsed.find("key_size_candidates"), Some(&vec![3usize, 2].to_json()));
+        assert_eq!(parsed.find_path(&["plaintext", "utf8_lossy"]), Some(&Json::String("hello".to_owned())));
+        let keys = parsed.find("keys").and_then(|k| k.as_array()).unwrap();
+        assert_eq!(keys[0].find_path(&["key", "utf8_lossy"]), Some(&Json::String("ICE".to_owned())));
+        assert_eq!(keys[0].find("key_size"), Some(&Json::U64(3)));
+
+        let empty = RepeatingXorResult::new(&ciphertext, vec![], &[]);
+        assert_eq!(empty.to_json().find("plaintext"), Some(&Json::Null));
+    }
+
+    #[test]
+    fn detection_json() {
+        let ciphertexts = vec![vec![0x00], vec![0x30, 0x31]];
+        let detection = SingleByteXorDetection {
+            index: 1,
+            candidate: SingleByteXorCandidate { key: 0x58, plaintext: b"hi".to_vec(), score: 0.5 },
+        };
+        let json = DetectionResult::new(&ciphertexts, &detection).to_json();
+        assert_eq!(json.find("index"), Some(&Json::U64(1)));
+        assert_eq!(json.find_path(&["ciphertext", "hex"]), Some(&Json::String("3031".to_owned())));
+        assert_eq!(json.find_path(&["result", "key"]), Some(&Json::U64(0x58)));
+    }
+}
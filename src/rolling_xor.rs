@@ -0,0 +1,236 @@
+use std::cmp::Ordering;
+use std::collections::btree_map::BTreeMap;
+use frequency_analysis;
+use repeating_xor::{self, KeyConstraints};
+
+// XOR cipher variants commonly seen in malware, where the key changes from byte to byte.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RollingXorCipher {
+    // c[i] = p[i] ^ (key + i * step)
+    IncrementingKey { key: u8, step: u8 },
+    // c[0] = p[0] ^ initial, c[i] = p[i] ^ c[i - 1]
+    CiphertextFeedback { initial: u8 },
+    // c[i] = (p[i] ^ key) + add
+    XorAdd { key: u8, add: u8 },
+    // c[i] = (p[i] ^ key) rotated left by rotation bits
+    XorRotateLeft { key: u8, rotation: u32 },
+    // c[i] = p[i] ^ key[i % key.len()] ^ (counter_start + i)
+    CounterKeySchedule { key: Vec<u8>, counter_start: u8 },
+}
+
+impl RollingXorCipher {
+    pub fn encode(&self, plaintext: &[u8]) -> Vec<u8> {
+        match *self {
+            RollingXorCipher::IncrementingKey { key, step } => plaintext
+                .iter()
+                .enumerate()
+                .map(|(i, p)| p ^ key.wrapping_add(step.wrapping_mul(i as u8)))
+                .collect(),
+            RollingXorCipher::CiphertextFeedback { initial } => {
+                let mut previous = initial;
+                plaintext
+                    .iter()
+                    .map(|p| {
+                        previous ^= p;
+                        previous
+                    })
+                    .collect()
+            }
+            RollingXorCipher::XorAdd { key, add } => {
+                plaintext.iter().map(|p| (p ^ key).wrapping_add(add)).collect()
+            }
+            RollingXorCipher::XorRotateLeft { key, rotation } => {
+                plaintext.iter().map(|p| (p ^ key).rotate_left(rotation)).collect()
+            }
+            RollingXorCipher::CounterKeySchedule { ref key, counter_start } => {
+                if key.is_empty() {
+                    return plaintext.to_vec();
+                }
+                plaintext
+                    .iter()
+                    .zip(key.iter().cycle())
+                    .enumerate()
+                    .map(|(i, (p, k))| p ^ k ^ counter_start.wrapping_add(i as u8))
+                    .collect()
+            }
+        }
+    }
+
+    pub fn decode(&self, ciphertext: &[u8]) -> Vec<u8> {
+        match *self {
+            RollingXorCipher::CiphertextFeedback { initial } => {
+                let mut previous = initial;
+                ciphertext
+                    .iter()
+                    .map(|&c| {
+                        let p = c ^ previous;
+                        previous = c;
+                        p
+                    })
+                    .collect()
+            }
+            RollingXorCipher::XorAdd { key, add } => {
+                ciphertext.iter().map(|c| c.wrapping_sub(add) ^ key).collect()
+            }
+            RollingXorCipher::XorRotateLeft { key, rotation } => {
+                ciphertext.iter().map(|c| c.rotate_right(rotation) ^ key).collect()
+            }
+            // The remaining variants are their own inverse
+            _ => self.encode(ciphertext),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollingXorCandidate {
+    pub cipher: RollingXorCipher,
+    pub plaintext: Vec<u8>,
+    // Character frequency score of the plaintext, higher is better
+    pub score: f32,
+}
+
+// Decodes the ciphertext with every cipher given, keeping the plausible plaintexts, best first.
+fn rank_candidates<I>(ciphertext: &[u8],
+                      ciphers: I,
+                      character_frequencies: &BTreeMap<char, f32>)
+                      -> Vec<RollingXorCandidate>
+    where I: Iterator<Item = RollingXorCipher> {
+    let mut candidates: Vec<RollingXorCandidate> = ciphers
+        .filter_map(|cipher| {
+            let plaintext = cipher.decode(ciphertext);
+            frequency_analysis::plaintext_score(&plaintext, character_frequencies).map(|score| {
+                RollingXorCandidate { cipher, plaintext, score }
+            })
+        })
+        .collect();
+    candidates.sort_by(|c1, c2| c2.score.partial_cmp(&c1.score).unwrap_or(Ordering::Equal));
+    candidates
+}
+
+fn all_bytes() -> impl Iterator<Item = u8> + Clone {
+    (0..256u16).map(|b| b as u8)
+}
+
+pub fn crack_incrementing_key_xor(ciphertext: &[u8],
+                                  character_frequencies: &BTreeMap<char, f32>)
+                                  -> Vec<RollingXorCandidate> {
+    let ciphers = all_bytes().flat_map(|key| {
+        all_bytes().map(move |step| RollingXorCipher::IncrementingKey { key, step })
+    });
+    rank_candidates(ciphertext, ciphers, character_frequencies)
+}
+
+// Only the first plaintext byte depends on the initial value, so the best few candidates will
+// usually differ in that byte alone.
+pub fn crack_ciphertext_feedback_xor(ciphertext: &[u8],
+                                     character_frequencies: &BTreeMap<char, f32>)
+                                     -> Vec<RollingXorCandidate> {
+    let ciphers = all_bytes().map(|initial| RollingXorCipher::CiphertextFeedback { initial });
+    rank_candidates(ciphertext, ciphers, character_frequencies)
+}
+
+pub fn crack_xor_add(ciphertext: &[u8],
+                     character_frequencies: &BTreeMap<char, f32>)
+                     -> Vec<RollingXorCandidate> {
+    let ciphers = all_bytes()
+        .flat_map(|key| all_bytes().map(move |add| RollingXorCipher::XorAdd { key, add }));
+    rank_candidates(ciphertext, ciphers, character_frequencies)
+}
+
+pub fn crack_xor_rotate_left(ciphertext: &[u8],
+                             character_frequencies: &BTreeMap<char, f32>)
+                             -> Vec<RollingXorCandidate> {
+    let ciphers = all_bytes().flat_map(|key| {
+        (0..8).map(move |rotation| RollingXorCipher::XorRotateLeft { key, rotation })
+    });
+    rank_candidates(ciphertext, ciphers, character_frequencies)
+}
+
+// Removes each possible counter and recovers the remaining repeating XOR key, subject to the
+// key constraints.
+pub fn crack_counter_key_schedule_xor(ciphertext: &[u8],
+                                      constraints: &KeyConstraints,
+                                      character_frequencies: &BTreeMap<char, f32>)
+                                      -> Result<Vec<RollingXorCandidate>, String> {
+    let mut ciphers = Vec::new();
+    for counter_start in all_bytes() {
+        let counter_removed = RollingXorCipher::CounterKeySchedule {
+            key: vec![0],
+            counter_start,
+        }.decode(ciphertext);
+        let keys = repeating_xor::find_repeating_xor_keys(&counter_removed,
+                                                          constraints,
+                                                          character_frequencies)?;
+        if let Some(candidate) = keys.into_iter().next() {
+            ciphers.push(RollingXorCipher::CounterKeySchedule {
+                key: candidate.key,
+                counter_start,
+            });
+        }
+    }
+    Ok(rank_candidates(ciphertext, ciphers.into_iter(), character_frequencies))
+}
+
+#[cfg(test)]
+mod tests {
+    use rolling_xor::{RollingXorCipher, crack_incrementing_key_xor, crack_ciphertext_feedback_xor,
+                      crack_xor_add, crack_xor_rotate_left, crack_counter_key_schedule_xor};
+    use repeating_xor::{KeyCharset, KeyConstraints};
+    use frequency_analysis::english_letter_frequencies;
+
+    static PLAINTEXT: &str = "Burning 'em, if you ain't quick and nimble\nI go crazy when I hear a cymbal";
+
+    #[test]
+    fn round_trips() {
+        let ciphers = [RollingXorCipher::IncrementingKey { key: 0x5a, step: 3 },
+                       RollingXorCipher::CiphertextFeedback { initial: 0x42 },
+                       RollingXorCipher::XorAdd { key: 0x13, add: 0x77 },
+                       RollingXorCipher::XorRotateLeft { key: 0xa5, rotation: 3 },
+                       RollingXorCipher::CounterKeySchedule { key: b"ICE".to_vec(), counter_start: 9 }];
+        for cipher in ciphers.iter() {
+            let ciphertext = cipher.encode(PLAINTEXT.as_bytes());
+            assert!(ciphertext != PLAINTEXT.as_bytes().to_vec());
+            assert_eq!(cipher.decode(&ciphertext), PLAINTEXT.as_bytes().to_vec());
+        }
+    }
+
+    #[test]
+    fn ciphertext_feedback_encoding() {
+        let cipher = RollingXorCipher::CiphertextFeedback { initial: 0x01 };
+        assert_eq!(cipher.encode(&[0x02, 0x04, 0x08]), vec![0x03, 0x07, 0x0f]);
+    }
+
+    #[test]
+    fn crack_single_byte_families() {
+        let frequencies = english_letter_frequencies();
+
+        let cipher = RollingXorCipher::IncrementingKey { key: 0x5a, step: 3 };
+        let candidates = crack_incrementing_key_xor(&cipher.encode(PLAINTEXT.as_bytes()), &frequencies);
+        assert_eq!(candidates[0].plaintext, PLAINTEXT.as_bytes().to_vec());
+
+        let cipher = RollingXorCipher::CiphertextFeedback { initial: 0x42 };
+        let candidates = crack_ciphertext_feedback_xor(&cipher.encode(PLAINTEXT.as_bytes()), &frequencies);
+        assert_eq!(candidates[0].plaintext[1..], PLAINTEXT.as_bytes()[1..]);
+        assert!(candidates.iter().any(|c| c.cipher == cipher));
+
+        let cipher = RollingXorCipher::XorAdd { key: 0x13, add: 0x77 };
+        let candidates = crack_xor_add(&cipher.encode(PLAINTEXT.as_bytes()), &frequencies);
+        assert_eq!(candidates[0].plaintext, PLAINTEXT.as_bytes().to_vec());
+
+        let cipher = RollingXorCipher::XorRotateLeft { key: 0xa5, rotation: 3 };
+        let candidates = crack_xor_rotate_left(&cipher.encode(PLAINTEXT.as_bytes()), &frequencies);
+        assert_eq!(candidates[0].cipher, cipher);
+    }
+
+    #[test]
+    fn crack_counter_key_schedule() {
+        let cipher = RollingXorCipher::CounterKeySchedule { key: b"ICE".to_vec(), counter_start: 9 };
+        let mut constraints = KeyConstraints::new();
+        constraints.charset = KeyCharset::PrintableAscii;
+        constraints.max_key_length = 4;
+        let candidates = crack_counter_key_schedule_xor(&cipher.encode(PLAINTEXT.as_bytes()),
+                                                        &constraints,
+                                                        &english_letter_frequencies()).unwrap();
+        assert_eq!(candidates[0].plaintext, PLAINTEXT.as_bytes().to_vec());
+    }
+}
@@ -1,5 +1,7 @@
 use std::iter::IntoIterator;
 use std::ops::BitXor;
+use std::cmp::Ordering;
+use std::collections::btree_map::BTreeMap;
 use utility;
 use frequency_analysis;
 
@@ -23,6 +25,57 @@ impl<'a, II> SingleByteXorDecodable for II
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct SingleByteXorCandidate {
+    pub key: u8,
+    pub plaintext: Vec<u8>,
+    // Character frequency score of the plaintext, higher is better
+    pub score: f32,
+}
+
+// Returns every key whose decode passes the textual heuristics, best first.
+pub fn find_single_byte_xor_candidates(bytes : &[u8],
+                                       character_frequencies : &BTreeMap<char, f32>)
+                                       -> Vec<SingleByteXorCandidate> {
+    let mut candidates : Vec<SingleByteXorCandidate> = (0..256u16)
+        .map(|k| k as u8)
+        .filter_map(|key| {
+            let plaintext : Vec<u8> = bytes.iter().map(|b| b ^ key).collect();
+            frequency_analysis::plaintext_score(&plaintext, character_frequencies)
+                .map(|score| SingleByteXorCandidate { key, plaintext, score })
+        })
+        .collect();
+    candidates.sort_by(|c1, c2| c2.score.partial_cmp(&c1.score).unwrap_or(Ordering::Equal));
+    candidates
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SingleByteXorDetection {
+    // Index of the ciphertext among those searched
+    pub index: usize,
+    pub candidate: SingleByteXorCandidate,
+}
+
+// Finds which of several ciphertexts are most likely single byte XOR encrypted text, by the best
+// candidate of each, best first.
+pub fn detect_single_byte_xor(ciphertexts : &[Vec<u8>],
+                              character_frequencies : &BTreeMap<char, f32>)
+                              -> Vec<SingleByteXorDetection> {
+    let mut detections : Vec<SingleByteXorDetection> = ciphertexts.iter()
+        .enumerate()
+        .filter_map(|(index, ciphertext)| {
+            find_single_byte_xor_candidates(ciphertext, character_frequencies)
+                .into_iter()
+                .next()
+                .map(|candidate| SingleByteXorDetection { index, candidate })
+        })
+        .collect();
+    detections.sort_by(|d1, d2| {
+        d2.candidate.score.partial_cmp(&d1.candidate.score).unwrap_or(Ordering::Equal)
+    });
+    detections
+}
+
 pub fn find_best_decodes_for_slice_heuristically(bit_strings : &[&[u8]]) -> Vec<String> {
     let mut best_decode_candidates : Vec<String> = Vec::new();
     for s in bit_strings {
@@ -59,8 +112,10 @@ mod tests {
     use std::borrow::Borrow;
     use utility;
     use frequency_analysis::FrequencyAnalysable;
+    use frequency_analysis::english_letter_frequencies;
     use single_byte_xor::{SingleByteXorDecodable, find_best_decodes_for_slice_heuristically,
-                          find_best_decodes_for_vec_heuristically};
+                          find_best_decodes_for_vec_heuristically, find_single_byte_xor_candidates,
+                          detect_single_byte_xor};
 
     #[test]
     fn frequencies_of_buffer() {
@@ -137,5 +192,22 @@ mod tests {
 
         assert_eq!(decode_candidates.remove(0), "Now that the party is jumping\n");
     }
-}
 
+    #[test]
+    fn single_byte_xor_candidates() {
+        let hex_bytes = "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736".from_hex().unwrap();
+        let candidates = find_single_byte_xor_candidates(&hex_bytes, &english_letter_frequencies());
+        assert_eq!(candidates[0].key, 0x58);
+        assert_eq!(candidates[0].plaintext, b"Cooking MC's like a pound of bacon".to_vec());
+    }
+
+    #[test]
+    fn detect_single_byte_xor_in_list_of_candidates() {
+        let ciphertexts = vec!["04050b447efd1efc28004ce63e85adb40c61d2cc3bf1d3a39c79f1091a3e96b810be".from_hex().unwrap(),
+                               "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736".from_hex().unwrap(),
+                               "f4930be3b09a0fd724ad4e1843e27494289c8b793e4bee12722fef52344aba8fe13e".from_hex().unwrap()];
+        let detections = detect_single_byte_xor(&ciphertexts, &english_letter_frequencies());
+        assert_eq!(detections[0].index, 1);
+        assert_eq!(detections[0].candidate.key, 0x58);
+    }
+}
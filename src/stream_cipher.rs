@@ -0,0 +1,15 @@
+use error::CryptoError;
+
+// A seekable stream cipher: one that can produce its keystream from any offset, so any part of
+// a message can be encrypted or decrypted alone. Encryption and decryption are the same XOR.
+pub trait StreamCipher {
+    // Encrypts or decrypts data in place as if it sat offset bytes into the stream.
+    fn apply_keystream(&self, offset: u64, data: &mut [u8]) -> Result<(), CryptoError>;
+
+    // Encrypts or decrypts a whole message from the start of the stream.
+    fn process(&self, data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let mut output = data.to_vec();
+        self.apply_keystream(0, &mut output)?;
+        Ok(output)
+    }
+}
@@ -1,5 +1,5 @@
 use std::iter::IntoIterator;
-use std::collections::btree_map::BTreeMap;
+use std::collections::btree_map::{BTreeMap, Entry};
 use std::cmp::Ordering;
 use frequency_analysis;
 use rust_hamming_distance::bitwise_hamming_distance::BitwiseHammingDistancable;
@@ -96,16 +96,163 @@ pub fn find_normalized_edit_distances(bytes: &[u8]) -> Result<Vec<(f32, usize)>,
      &(ed2, _)| {
         ed1.partial_cmp(&ed2).unwrap()
     });
-    for &(ed, len) in normalised_edit_distance_and_lengths.iter() {
-        println!("{:?}, {:?}", ed, len);
-    }
     Ok(normalised_edit_distance_and_lengths)
 }
 
+// Returns a list of (normalised edit distance, length) for lengths in [min_length, max_length],
+// sorted by distance. Here the edit distance is averaged over every pair of consecutive
+// length sized blocks, which is considerably less noisy than only comparing the first two.
+pub fn find_averaged_edit_distances(
+    bytes: &[u8],
+    min_length: usize,
+    max_length: usize,
+) -> Result<Vec<(f32, usize)>, String> {
+    if bytes.len() < 2 {
+        return Err(
+            "Cannot find edit distance for bytes of len: ".to_owned() + &bytes.len().to_string(),
+        );
+    }
+    let mut averaged_edit_distance_and_lengths = Vec::new();
+    for possible_key_len in min_length.max(1)..max_length + 1 {
+        let num_blocks = bytes.len() / possible_key_len;
+        if num_blocks < 2 {
+            break;
+        }
+        let mut total_edit_distance = 0;
+        for i in 0..num_blocks - 1 {
+            let first = &bytes[i * possible_key_len..(i + 1) * possible_key_len];
+            let second = &bytes[(i + 1) * possible_key_len..(i + 2) * possible_key_len];
+            match first.bitwise_hamming_distance(second) {
+                Ok(ed) => total_edit_distance += ed,
+                Err(e) => return Err(e.to_owned()),
+            }
+        }
+        let averaged_ed =
+            (total_edit_distance as f32) / (((num_blocks - 1) * possible_key_len) as f32);
+        averaged_edit_distance_and_lengths.push((averaged_ed, possible_key_len));
+    }
+    averaged_edit_distance_and_lengths.sort_by(|&(ed1, _), &(ed2, _)| {
+        ed1.partial_cmp(&ed2).unwrap_or(Ordering::Equal)
+    });
+    Ok(averaged_edit_distance_and_lengths)
+}
+
+// Returns a list of (mean index of coincidence, length) for lengths in [min_length, max_length],
+// highest first. Splitting a polyalphabetic ciphertext into columns by the right key length
+// leaves each column encrypted with a single key element, which preserves the plaintext's
+// index of coincidence.
+pub fn find_coincidence_key_lengths(
+    bytes: &[u8],
+    min_length: usize,
+    max_length: usize,
+) -> Result<Vec<(f32, usize)>, String> {
+    if bytes.len() < 2 {
+        return Err(
+            "Cannot find index of coincidence for bytes of len: ".to_owned() +
+                &bytes.len().to_string(),
+        );
+    }
+    let mut coincidence_and_lengths = Vec::new();
+    for possible_key_len in min_length.max(1)..max_length + 1 {
+        if bytes.len() / possible_key_len < 2 {
+            break;
+        }
+        let total_coincidence: f32 = (0..possible_key_len)
+            .map(|offset| {
+                let column: Vec<u8> =
+                    bytes[offset..].iter().step_by(possible_key_len).cloned().collect();
+                frequency_analysis::index_of_coincidence(column)
+            })
+            .sum();
+        coincidence_and_lengths.push((total_coincidence / possible_key_len as f32, possible_key_len));
+    }
+    // Stable, so multiples of the key length don't overtake it on ties
+    coincidence_and_lengths.sort_by(|&(ioc1, _), &(ioc2, _)| {
+        ioc2.partial_cmp(&ioc1).unwrap_or(Ordering::Equal)
+    });
+    Ok(coincidence_and_lengths)
+}
+
+// Kasiski examination: returns a list of (number of repeated trigram distances the length
+// divides, length) for lengths in [min_length, max_length], most first. Lengths dividing no
+// distance are omitted.
+pub fn find_kasiski_key_lengths(
+    bytes: &[u8],
+    min_length: usize,
+    max_length: usize,
+) -> Vec<(usize, usize)> {
+    let mut last_positions: BTreeMap<&[u8], usize> = BTreeMap::new();
+    let mut distances = Vec::new();
+    for (position, trigram) in bytes.windows(3).enumerate() {
+        match last_positions.entry(trigram) {
+            Entry::Vacant(entry) => {
+                entry.insert(position);
+            }
+            Entry::Occupied(mut entry) => {
+                distances.push(position - *entry.get());
+                entry.insert(position);
+            }
+        }
+    }
+    let mut counts_and_lengths: Vec<(usize, usize)> = (min_length.max(1)..max_length + 1)
+        .map(|length| (distances.iter().filter(|&d| d % length == 0).count(), length))
+        .filter(|&(count, _)| count > 0)
+        .collect();
+    // Every divisor of the key length divides as many distances as the key length itself, so
+    // prefer longer lengths on ties
+    counts_and_lengths.sort_by(|&(count1, length1), &(count2, length2)| {
+        count2.cmp(&count1).then(length2.cmp(&length1))
+    });
+    counts_and_lengths
+}
+
+// Combines several rankings of key lengths, each best first, by summing each length's position
+// in every ranking. Lengths missing from a ranking are placed after all of its entries.
+pub fn rank_key_lengths(rankings: &[Vec<usize>]) -> Vec<usize> {
+    let mut positions: BTreeMap<usize, usize> = BTreeMap::new();
+    for ranking in rankings {
+        for &length in ranking {
+            positions.entry(length).or_insert(0);
+        }
+    }
+    let lengths: Vec<usize> = positions.keys().cloned().collect();
+    for ranking in rankings {
+        for &length in &lengths {
+            let position = ranking.iter().position(|&l| l == length).unwrap_or(ranking.len());
+            *positions.get_mut(&length).unwrap() += position;
+        }
+    }
+    let mut ranked: Vec<(usize, usize)> = positions.into_iter().map(|(l, p)| (p, l)).collect();
+    ranked.sort();
+    ranked.into_iter().map(|(_, length)| length).collect()
+}
+
+// Ranks likely key lengths of a repeating key ciphertext by combining the averaged edit
+// distance, index of coincidence and Kasiski estimators.
+pub fn find_key_length_candidates(
+    bytes: &[u8],
+    min_length: usize,
+    max_length: usize,
+) -> Result<Vec<usize>, String> {
+    let edit_distances = find_averaged_edit_distances(bytes, min_length, max_length)?;
+    let coincidences = find_coincidence_key_lengths(bytes, min_length, max_length)?;
+    let kasiski = find_kasiski_key_lengths(bytes, min_length, max_length);
+    let mut candidates = rank_key_lengths(&[
+        edit_distances.into_iter().map(|(_, l)| l).collect(),
+        coincidences.into_iter().map(|(_, l)| l).collect(),
+        kasiski.into_iter().map(|(_, l)| l).collect(),
+    ]);
+    // Kasiski may suggest lengths too long for the others to have considered
+    candidates.retain(|&l| bytes.len() / l >= 2);
+    Ok(candidates)
+}
+
 #[cfg(test)]
+#[allow(clippy::excessive_precision)]
 mod tests {
     use utility::ApproxEquality;
-    use utility::find_normalized_edit_distances;
+    use utility::{find_normalized_edit_distances, find_averaged_edit_distances,
+                  find_coincidence_key_lengths, find_kasiski_key_lengths, rank_key_lengths};
 
     #[test]
     fn f32_approx_equal() {
@@ -143,4 +290,47 @@ mod tests {
         assert!(edit_distances4[0].0.approx_equal(0f32));
         assert!(edit_distances4[0].1 == 2);
     }
+
+    #[test]
+    fn averaged_edit_distances() {
+        let bytes = [0x01, 0x02, 0x01, 0x02, 0x01, 0x03]; // 0.25 at len 2, 1.66.. at len 3
+
+        let edit_distances = find_averaged_edit_distances(&bytes, 2, 3).unwrap();
+        assert_eq!(edit_distances.len(), 2);
+        assert!(edit_distances[0].0.approx_equal(0.25f32));
+        assert!(edit_distances[0].1 == 2);
+        assert!(edit_distances[1].0.approx_equal(1.6666666f32));
+        assert!(edit_distances[1].1 == 3);
+
+        assert!(find_averaged_edit_distances(&[0x01], 1, 3).is_err());
+    }
+
+    #[test]
+    fn coincidence_key_lengths() {
+        // Columns at length 3 are constant
+        let bytes = [0x01, 0x02, 0x03, 0x01, 0x02, 0x03, 0x01, 0x02, 0x03, 0x01, 0x02, 0x03];
+
+        let coincidences = find_coincidence_key_lengths(&bytes, 1, 6).unwrap();
+        assert!(coincidences[0].0.approx_equal(1f32));
+        assert!(coincidences[0].1 == 3);
+        // Multiples also have constant columns, but rank after the shortest
+        assert!(coincidences[1].1 == 6);
+
+        assert!(find_coincidence_key_lengths(&[0x01], 1, 3).is_err());
+    }
+
+    #[test]
+    fn kasiski_key_lengths() {
+        // "abc" repeats twice at a distance of 5
+        let bytes = b"abcxyabcqrabcwz";
+
+        let kasiski = find_kasiski_key_lengths(bytes, 2, 10);
+        assert_eq!(kasiski, vec![(2, 5)]);
+    }
+
+    #[test]
+    fn ranked_key_lengths() {
+        let ranked = rank_key_lengths(&[vec![3, 5, 4], vec![5, 3, 4], vec![3]]);
+        assert_eq!(ranked, vec![3, 5, 4]);
+    }
 }